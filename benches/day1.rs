@@ -0,0 +1,66 @@
+use aoc2024::day1::{self, sort::sort};
+use criterion::{black_box, criterion_group, criterion_main, BatchSize, Criterion};
+use itertools::Itertools;
+use std::fs;
+
+fn sorting(c: &mut Criterion) {
+    let input = fs::read_to_string("input/2024/day1.txt").unwrap();
+    let (left, _) = day1::parse(&input).unwrap();
+    let mut state = 2024u64;
+    let mut random = move || {
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        state
+    };
+    // a hundred times as many values over the same range as the input, over a range that needs
+    // a few radix passes, and spread too wide for those to pay off
+    let large = (0..100_000)
+        .map(|_| 10_000 + (random() % 90_000) as i64)
+        .collect_vec();
+    let spread = (0..100_000)
+        .map(|_| (random() % (1 << 30)) as i64)
+        .collect_vec();
+    let wide = (0..100_000).map(|_| random() as i64).collect_vec();
+
+    let mut group = c.benchmark_group("day1 sort");
+    for (name, values) in [
+        ("input", &left),
+        ("large", &large),
+        ("spread", &spread),
+        ("wide", &wide),
+    ] {
+        group.bench_function(format!("itertools sorted/{name}"), |b| {
+            b.iter(|| values.iter().copied().sorted().collect_vec())
+        });
+        group.bench_function(format!("sort_unstable/{name}"), |b| {
+            b.iter_batched_ref(
+                || values.clone(),
+                |values| values.sort_unstable(),
+                BatchSize::SmallInput,
+            )
+        });
+        group.bench_function(format!("counting or radix/{name}"), |b| {
+            b.iter_batched_ref(
+                || values.clone(),
+                |values| sort(values),
+                BatchSize::SmallInput,
+            )
+        });
+    }
+    group.finish();
+
+    let mut group = c.benchmark_group("day1");
+    group.bench_function("part1 itertools", |b| {
+        b.iter(|| day1::part1_itertools(black_box(&input)))
+    });
+    group.bench_function("part1", |b| b.iter(|| day1::part1(black_box(&input))));
+    group.bench_function("part2 hash map", |b| {
+        b.iter(|| day1::part2_hash_map(black_box(&input)))
+    });
+    group.bench_function("part2", |b| b.iter(|| day1::part2(black_box(&input))));
+    group.finish();
+}
+
+criterion_group!(benches, sorting);
+criterion_main!(benches);
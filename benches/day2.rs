@@ -0,0 +1,68 @@
+use aoc2024::day2::{is_safe, line_is_valid, parse_levels, policy::Policy, report_is_safe};
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    fs,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+// counts every allocation, so each way of checking reports can say how many it makes
+struct Counting;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: Counting = Counting;
+
+fn allocations(f: impl FnOnce() -> usize) -> usize {
+    let before = ALLOCATIONS.load(Ordering::Relaxed);
+    black_box(f());
+    ALLOCATIONS.load(Ordering::Relaxed) - before
+}
+
+fn checking(c: &mut Criterion) {
+    let input = fs::read_to_string("input/2024/day2.txt").unwrap();
+    let policy = Policy::dampened();
+    let combinations = || input.lines().filter(|line| line_is_valid(line, 1)).count();
+    let vec_levels = || {
+        input
+            .lines()
+            .filter(|line| is_safe(&parse_levels(line), &policy))
+            .count()
+    };
+    let stack_levels = || {
+        input
+            .lines()
+            .filter(|line| report_is_safe(line, &policy))
+            .count()
+    };
+
+    for (name, count) in [
+        ("combinations", allocations(combinations)),
+        ("vec levels", allocations(vec_levels)),
+        ("stack levels", allocations(stack_levels)),
+    ] {
+        println!("day2 part2/{name}: {count} allocations");
+    }
+    assert_eq!(allocations(stack_levels), 0);
+
+    let mut group = c.benchmark_group("day2 part2");
+    group.bench_function("combinations", |b| b.iter(combinations));
+    group.bench_function("vec levels", |b| b.iter(vec_levels));
+    group.bench_function("stack levels", |b| b.iter(stack_levels));
+    group.finish();
+}
+
+criterion_group!(benches, checking);
+criterion_main!(benches);
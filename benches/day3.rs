@@ -0,0 +1,56 @@
+use aoc2024::day3::{
+    evaluate,
+    generate::generate_spaced,
+    lexer::{Grammar, Lexer},
+    read_memory,
+    stream::StreamEvaluator,
+    Accumulator,
+};
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+
+// the state machine reads do and don't the loose way, so that's what it's compared against
+fn lexer(memory: &str) -> i128 {
+    evaluate(
+        Lexer::with_grammar(memory, Grammar::Loose),
+        true,
+        Accumulator::Checked64,
+    )
+    .unwrap()
+}
+
+fn stream(memory: &str) -> i128 {
+    let evaluator = StreamEvaluator {
+        grammar: Grammar::Loose,
+        ..StreamEvaluator::new(true)
+    };
+    evaluator.evaluate(memory.as_bytes()).unwrap()
+}
+
+fn scanning(c: &mut Criterion) {
+    let mut group = c.benchmark_group("day3 part2");
+    for len in [64 * 1024, 1024 * 1024, 16 * 1024 * 1024] {
+        let memory = generate_spaced(len, 2024);
+        let expected = read_memory(&memory, true) as i128;
+        assert_eq!(lexer(&memory), expected);
+        assert_eq!(stream(&memory), expected);
+
+        group.throughput(Throughput::Bytes(len as u64));
+        group.bench_with_input(
+            BenchmarkId::new("state machine", len),
+            &memory,
+            |b, memory| b.iter(|| read_memory(black_box(memory), true)),
+        );
+        group.bench_with_input(
+            BenchmarkId::new("memmem lexer", len),
+            &memory,
+            |b, memory| b.iter(|| lexer(black_box(memory))),
+        );
+        group.bench_with_input(BenchmarkId::new("stream", len), &memory, |b, memory| {
+            b.iter(|| stream(black_box(memory)))
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, scanning);
+criterion_main!(benches);
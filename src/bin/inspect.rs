@@ -0,0 +1,138 @@
+use aoc2024::{
+    day2::{self, explain::diagnose, policy::Policy},
+    day3::trace::Tracer,
+    day5::{self, explain::Diagnosis, index::PageIndex, DailyInput, Update},
+};
+use itertools::Itertools;
+use std::{error::Error, fs, process::ExitCode};
+
+const USAGE: &str = "usage:
+    inspect day2 explain <policy> [--json] [input] explain why each report is safe or not
+    inspect day2 count <policy> [input]            count the reports that are safe
+    inspect day3 trace <part1|part2> [--near-misses] [input]
+                                                   list every instruction and the running total
+    inspect day5 explain <update|all> [input]      explain why updates break the rules
+    inspect day5 dot <update|all> [input]          draw the rules an update uses, or all of them,
+                                                   as a Graphviz graph
+    inspect day5 orderings <update|all> [input]    count the valid orderings of updates";
+
+fn day2_explain(policy: &str, json: bool, input_path: Option<&str>) -> Result<(), Box<dyn Error>> {
+    let policy: Policy = policy.parse()?;
+    let input = fs::read_to_string(input_path.unwrap_or("input/2024/day2.txt"))?;
+    for (line, diagnosis) in diagnose(&input, &policy).iter().enumerate() {
+        match json {
+            true => println!("{}", diagnosis.to_json()),
+            false => println!("{:>5}  {diagnosis}", line + 1),
+        }
+    }
+    Ok(())
+}
+
+fn day2_count(policy: &str, input_path: Option<&str>) -> Result<(), Box<dyn Error>> {
+    let policy: Policy = policy.parse()?;
+    let input = fs::read_to_string(input_path.unwrap_or("input/2024/day2.txt"))?;
+    let safe = input
+        .lines()
+        .filter(|line| day2::is_safe(&day2::parse_levels(line), &policy))
+        .count();
+    println!("{safe}");
+    Ok(())
+}
+
+fn day3_trace(
+    part: &str,
+    near_misses: bool,
+    input_path: Option<&str>,
+) -> Result<(), Box<dyn Error>> {
+    let do_do = match part {
+        "part1" => false,
+        "part2" => true,
+        part => return Err(format!("unknown part {part}").into()),
+    };
+    let input = fs::read_to_string(input_path.unwrap_or("input/2024/day3.txt"))?;
+    let tracer = Tracer {
+        near_misses,
+        ..Tracer::new(do_do)
+    };
+    for entry in tracer.trace(&input)? {
+        println!("{entry}");
+    }
+    Ok(())
+}
+
+// Reads the day5 input, and the updates selected on the command line
+fn day5_input(
+    update: &str,
+    input_path: Option<&str>,
+) -> Result<(DailyInput, Vec<Update>), Box<dyn Error>> {
+    let input = day5::parse(&fs::read_to_string(
+        input_path.unwrap_or("input/2024/day5.txt"),
+    )?)?;
+    let updates = match update {
+        "all" => input.updates.clone(),
+        update => vec![day5::parse_update(update)?],
+    };
+    Ok((input, updates))
+}
+
+fn day5_explain(update: &str, input_path: Option<&str>) -> Result<(), Box<dyn Error>> {
+    let (input, updates) = day5_input(update, input_path)?;
+    for update in &updates {
+        print!("{}", Diagnosis::new(&input.rules, update)?);
+    }
+    Ok(())
+}
+
+fn day5_dot(update: &str, input_path: Option<&str>) -> Result<(), Box<dyn Error>> {
+    let (input, updates) = day5_input(update, input_path)?;
+    let update = (update != "all").then(|| &updates[0]);
+    print!("{}", input.to_dot(update));
+    Ok(())
+}
+
+fn day5_orderings(update: &str, input_path: Option<&str>) -> Result<(), Box<dyn Error>> {
+    let (input, updates) = day5_input(update, input_path)?;
+    let index = PageIndex::new(&input.rules);
+    for update in &updates {
+        let pages = update.iter().join(",");
+        match index.orderings(update) {
+            Ok(orderings) => println!("update {pages}: {orderings}"),
+            Err(err) => println!("update {pages}: {err}"),
+        }
+    }
+    Ok(())
+}
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+    let result = match args.as_slice() {
+        ["day2", "explain", policy] => day2_explain(policy, false, None),
+        ["day2", "explain", policy, "--json"] => day2_explain(policy, true, None),
+        ["day2", "explain", policy, "--json", input] => day2_explain(policy, true, Some(input)),
+        ["day2", "explain", policy, input] => day2_explain(policy, false, Some(input)),
+        ["day2", "count", policy] => day2_count(policy, None),
+        ["day2", "count", policy, input] => day2_count(policy, Some(input)),
+        ["day3", "trace", part] => day3_trace(part, false, None),
+        ["day3", "trace", part, "--near-misses"] => day3_trace(part, true, None),
+        ["day3", "trace", part, "--near-misses", input] => day3_trace(part, true, Some(input)),
+        ["day3", "trace", part, input] => day3_trace(part, false, Some(input)),
+        ["day5", "explain", update] => day5_explain(update, None),
+        ["day5", "explain", update, input] => day5_explain(update, Some(input)),
+        ["day5", "dot", update] => day5_dot(update, None),
+        ["day5", "dot", update, input] => day5_dot(update, Some(input)),
+        ["day5", "orderings", update] => day5_orderings(update, None),
+        ["day5", "orderings", update, input] => day5_orderings(update, Some(input)),
+        _ => {
+            eprintln!("{USAGE}");
+            return ExitCode::FAILURE;
+        }
+    };
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("error: {err}");
+            ExitCode::FAILURE
+        }
+    }
+}
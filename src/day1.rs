@@ -1,4 +1,8 @@
 use itertools::Itertools;
+use sort::{sort, Counts};
+use std::{error::Error, fmt::Display};
+
+pub mod sort;
 
 #[allow(dead_code)]
 fn builtin_parse(input: &str, left: &mut Vec<usize>, rght: &mut Vec<usize>) {
@@ -9,59 +13,183 @@ fn builtin_parse(input: &str, left: &mut Vec<usize>, rght: &mut Vec<usize>) {
     });
 }
 
-#[allow(dead_code)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ParseErrorKind {
+    // not an optionally signed number that fits in an i64
+    InvalidNumber,
+    MissingColumn,
+    ExtraColumn,
+}
+
+/// The first thing in the input that isn't part of two columns of numbers. Lines and columns
+/// start at 1.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ParseError {
+    pub line: usize,
+    pub column: usize,
+    pub kind: ParseErrorKind,
+}
+
+impl Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "line {}, column {}: ", self.line, self.column)?;
+        match self.kind {
+            ParseErrorKind::InvalidNumber => write!(f, "invalid number"),
+            ParseErrorKind::MissingColumn => write!(f, "expected two numbers"),
+            ParseErrorKind::ExtraColumn => write!(f, "expected only two numbers"),
+        }
+    }
+}
+
+impl Error for ParseError {}
+
+// reads the number starting at `pos`, returning it and the index after it
 #[inline(always)]
-fn custom_parse(input: &str, left: &mut Vec<usize>, rght: &mut Vec<usize>) {
-    let mut val = 0;
-    input.as_bytes().iter().for_each(|b| match b {
-        b' ' => {
-            if val != 0 {
-                left.push(val);
+fn parse_number(line: &[u8], pos: usize) -> Option<(i64, usize)> {
+    let (negative, start) = match line[pos] {
+        b'-' => (true, pos + 1),
+        b'+' => (false, pos + 1),
+        _ => (false, pos),
+    };
+    let mut end = start;
+    let mut val: i64 = 0;
+    while let Some(&byte) = line.get(end).filter(|byte| !byte.is_ascii_whitespace()) {
+        let digit = byte.wrapping_sub(b'0');
+        if digit > 9 {
+            return None;
+        }
+        // built up negative so i64::MIN fits
+        val = val.checked_mul(10)?.checked_sub(digit as i64)?;
+        end += 1;
+    }
+    if end == start {
+        return None;
+    }
+    let val = if negative { val } else { val.checked_neg()? };
+    Some((val, end))
+}
+
+/// Reads the two columns of numbers, separated by any whitespace. Blank lines are skipped, and
+/// lines may end in `\r\n`.
+pub fn parse(input: &str) -> Result<(Vec<i64>, Vec<i64>), ParseError> {
+    let mut left = Vec::with_capacity(1000);
+    let mut rght = Vec::with_capacity(1000);
+
+    for (idx, line) in input.as_bytes().split(|b| *b == b'\n').enumerate() {
+        let error = |pos: usize, kind| ParseError {
+            line: idx + 1,
+            column: pos + 1,
+            kind,
+        };
+        let skip_space = |mut pos: usize| {
+            while line.get(pos).is_some_and(u8::is_ascii_whitespace) {
+                pos += 1;
             }
-            val = 0;
+            pos
+        };
+
+        let pos = skip_space(0);
+        if pos == line.len() {
+            continue;
         }
-        b'\n' => {
-            rght.push(val);
-            val = 0;
+        let (l, pos) =
+            parse_number(line, pos).ok_or_else(|| error(pos, ParseErrorKind::InvalidNumber))?;
+        let pos = skip_space(pos);
+        if pos == line.len() {
+            return Err(error(pos, ParseErrorKind::MissingColumn));
         }
-        b'0'..=b'9' => {
-            val *= 10;
-            val += *b as usize - 48;
+        let (r, pos) =
+            parse_number(line, pos).ok_or_else(|| error(pos, ParseErrorKind::InvalidNumber))?;
+        let pos = skip_space(pos);
+        if pos != line.len() {
+            return Err(error(pos, ParseErrorKind::ExtraColumn));
         }
-        _ => panic!("im tired of this grampa"),
-    });
+        left.push(l);
+        rght.push(r);
+    }
+    Ok((left, rght))
+}
+
+/// Why the lists couldn't be compared
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ListError {
+    Parse(ParseError),
+    // the answer doesn't fit in an i128
+    Overflow,
+}
 
-    // file not ended by a \n
-    if val != 0 {
-        rght.push(val);
+impl From<ParseError> for ListError {
+    fn from(err: ParseError) -> Self {
+        ListError::Parse(err)
     }
 }
 
-#[aoc(day1, part1)]
-pub fn part1(input: &str) -> usize {
-    let mut left = Vec::with_capacity(1000);
-    let mut rght = Vec::with_capacity(1000);
+impl Display for ListError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ListError::Parse(err) => write!(f, "{err}"),
+            ListError::Overflow => write!(f, "the answer is too large"),
+        }
+    }
+}
+
+impl Error for ListError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ListError::Parse(err) => Some(err),
+            ListError::Overflow => None,
+        }
+    }
+}
+
+// the distances between any two i64s fit in a u64, so only the total needs checking
+fn total_distance(mut pairs: impl Iterator<Item = (i64, i64)>) -> Result<u128, ListError> {
+    pairs.try_fold(0u128, |total, (l, r)| {
+        total
+            .checked_add(l.abs_diff(r) as u128)
+            .ok_or(ListError::Overflow)
+    })
+}
+
+fn similarity(left: &[i64], count: impl Fn(i64) -> usize) -> Result<i128, ListError> {
+    left.iter().try_fold(0i128, |total, val| {
+        (*val as i128)
+            .checked_mul(count(*val) as i128)
+            .and_then(|score| total.checked_add(score))
+            .ok_or(ListError::Overflow)
+    })
+}
 
-    custom_parse(input, &mut left, &mut rght);
+#[aoc(day1, part1)]
+pub fn part1(input: &str) -> Result<u128, ListError> {
+    let (mut left, mut rght) = parse(input)?;
+    sort(&mut left);
+    sort(&mut rght);
 
-    left.into_iter()
-        .sorted()
-        .zip(rght.into_iter().sorted())
-        .map(|(l, r)| l.abs_diff(r))
-        .sum()
+    total_distance(left.into_iter().zip(rght))
 }
 
 #[aoc(day1, part2)]
-pub fn part2(input: &str) -> usize {
-    let mut left = Vec::with_capacity(1000);
-    let mut rght = Vec::with_capacity(1000);
+pub fn part2(input: &str) -> Result<i128, ListError> {
+    let (left, rght) = parse(input)?;
+    let counts = Counts::new(&rght);
+
+    similarity(&left, |val| counts.get(val))
+}
 
-    custom_parse(input, &mut left, &mut rght);
+#[aoc(day1, part1, itertools)]
+pub fn part1_itertools(input: &str) -> Result<u128, ListError> {
+    let (left, rght) = parse(input)?;
+
+    total_distance(left.into_iter().sorted().zip(rght.into_iter().sorted()))
+}
+
+#[aoc(day1, part2, hash_map)]
+pub fn part2_hash_map(input: &str) -> Result<i128, ListError> {
+    let (left, rght) = parse(input)?;
     let rmap = rght.into_iter().counts();
 
-    left.into_iter()
-        .map(|val| val * rmap.get(&val).unwrap_or(&0))
-        .sum()
+    similarity(&left, |val| *rmap.get(&val).unwrap_or(&0))
 }
 
 #[cfg(test)]
@@ -77,11 +205,73 @@ mod test {
 
     #[test]
     fn part1_real_input() {
-        assert_eq!(part1(&get_input()), 1530215)
+        assert_eq!(part1(&get_input()), Ok(1530215))
     }
 
     #[test]
     fn part2_real_input() {
-        assert_eq!(part2(&get_input()), 26800609)
+        assert_eq!(part2(&get_input()), Ok(26800609))
+    }
+
+    #[test]
+    fn fast_paths_match_itertools() {
+        let input = get_input();
+        assert_eq!(part1(&input), part1_itertools(&input));
+        assert_eq!(part2(&input), part2_hash_map(&input));
+
+        let wide = "-5000000000 7\n12 -3\n9000000000000 12\n7 12\n";
+        assert_eq!(part1(wide), part1_itertools(wide));
+        assert_eq!(part2(wide), part2_hash_map(wide));
+    }
+
+    #[test]
+    fn keeps_zeros() {
+        assert_eq!(
+            parse("0   3\n4 0\n0 0\n"),
+            Ok((vec![0, 4, 0], vec![3, 0, 0]))
+        );
+    }
+
+    #[test]
+    fn any_whitespace_and_signs() {
+        assert_eq!(
+            parse("3\t4\r\n  -2   +5  \r\n\r\n\n-9223372036854775808 9223372036854775807"),
+            Ok((vec![3, -2, i64::MIN], vec![4, 5, i64::MAX]))
+        );
+        assert_eq!(part1("1 -1\n-3 3"), Ok(4));
+        assert_eq!(part2("-1 -1\n-1 2\n2 -1"), Ok(-2));
+    }
+
+    #[test]
+    fn answers_beyond_i64() {
+        let extremes = "-9223372036854775808 9223372036854775807\n".repeat(2);
+        let expected = 2 * u64::MAX as u128;
+        assert_eq!(part1(&extremes), Ok(expected));
+        assert_eq!(part1_itertools(&extremes), Ok(expected));
+
+        let largest = "9223372036854775807 9223372036854775807\n".repeat(2);
+        let expected = 4 * i64::MAX as i128;
+        assert_eq!(part2(&largest), Ok(expected));
+        assert_eq!(part2_hash_map(&largest), Ok(expected));
+    }
+
+    #[test]
+    fn malformed_lines() {
+        let error = |line, column, kind| Err(ParseError { line, column, kind });
+        assert_eq!(
+            parse("1 2\n3\n"),
+            error(2, 2, ParseErrorKind::MissingColumn)
+        );
+        assert_eq!(parse("1 2 3"), error(1, 5, ParseErrorKind::ExtraColumn));
+        assert_eq!(
+            parse("1 2\n1 x2"),
+            error(2, 3, ParseErrorKind::InvalidNumber)
+        );
+        assert_eq!(parse("- 2"), error(1, 1, ParseErrorKind::InvalidNumber));
+        assert_eq!(parse("1 1-2"), error(1, 3, ParseErrorKind::InvalidNumber));
+        assert_eq!(
+            parse("9223372036854775808 1"),
+            error(1, 1, ParseErrorKind::InvalidNumber)
+        );
     }
 }
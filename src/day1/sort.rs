@@ -0,0 +1,174 @@
+use itertools::Itertools;
+use std::collections::HashMap;
+
+/// Values spread over this many possibilities or more are never counted into a dense array,
+/// however many of them there are
+pub const DENSE_SPAN: u64 = 1 << 20;
+
+// how far every value is from `min`, which orders the same as the values themselves
+fn offset(value: i64, min: i64) -> u64 {
+    (value as u64).wrapping_sub(min as u64)
+}
+
+// the smallest value and how far the largest is from it
+fn range(values: &[i64]) -> Option<(i64, u64)> {
+    let (min, max) = values.iter().minmax().into_option()?;
+    Some((*min, offset(*max, *min)))
+}
+
+// whether counting into an array of `span + 1` slots costs about as much as the `len` values
+// being counted, so the array is neither big nor mostly empty
+fn is_dense(span: u64, len: usize) -> bool {
+    span < DENSE_SPAN && span <= 2 * len as u64
+}
+
+/// Lists shorter than this aren't worth the radix sort's passes and scratch space
+pub const RADIX_MIN_LEN: usize = 1 << 12;
+
+/// Sorts in place with a counting sort when there are about as many values as possibilities, a
+/// radix sort over only as many bytes as they differ by when there are lots of values and few
+/// bytes, and the standard library otherwise
+pub fn sort(values: &mut [i64]) {
+    let Some((min, span)) = range(values) else {
+        return;
+    };
+    if is_dense(span, values.len()) {
+        counting_sort(values, min, span);
+    } else if values.len() >= RADIX_MIN_LEN && span <= u32::MAX as u64 {
+        radix_sort(values, min, span);
+    } else {
+        values.sort_unstable();
+    }
+}
+
+fn counting_sort(values: &mut [i64], min: i64, span: u64) {
+    let mut counts = vec![0u32; span as usize + 1];
+    for value in values.iter() {
+        counts[offset(*value, min) as usize] += 1;
+    }
+    let mut pos = 0;
+    for (idx, count) in counts.into_iter().enumerate() {
+        let value = min.wrapping_add(idx as i64);
+        values[pos..pos + count as usize].fill(value);
+        pos += count as usize;
+    }
+}
+
+// least significant byte first, so each pass keeps the order the one before it made
+fn radix_sort(values: &mut [i64], min: i64, span: u64) {
+    let bytes = (u64::BITS - span.leading_zeros()).div_ceil(8);
+    let mut scratch = vec![0; values.len()];
+    let (mut from, mut to) = (&mut *values, &mut scratch[..]);
+    for byte in 0..bytes {
+        let digit = |value: &i64| (offset(*value, min) >> (byte * 8)) as usize & 0xff;
+        let mut starts = [0usize; 256];
+        for value in from.iter() {
+            starts[digit(value)] += 1;
+        }
+        let mut total = 0;
+        for start in starts.iter_mut() {
+            (*start, total) = (total, total + *start);
+        }
+        for value in from.iter() {
+            let digit = digit(value);
+            to[starts[digit]] = *value;
+            starts[digit] += 1;
+        }
+        (from, to) = (to, from);
+    }
+    if bytes % 2 == 1 {
+        values.copy_from_slice(&scratch);
+    }
+}
+
+/// How many times each value appears, in a dense array when the values are close together and
+/// there are about as many of them as possibilities
+pub enum Counts {
+    Dense { min: i64, counts: Vec<u32> },
+    Sparse(HashMap<i64, usize>),
+}
+
+impl Counts {
+    pub fn new(values: &[i64]) -> Self {
+        match range(values) {
+            Some((min, span)) if is_dense(span, values.len()) => {
+                let mut counts = vec![0; span as usize + 1];
+                for value in values {
+                    counts[offset(*value, min) as usize] += 1;
+                }
+                Counts::Dense { min, counts }
+            }
+            _ => Counts::Sparse(values.iter().copied().counts()),
+        }
+    }
+
+    pub fn get(&self, value: i64) -> usize {
+        match self {
+            Counts::Dense { min, counts } => value
+                .checked_sub(*min)
+                .and_then(|offset| usize::try_from(offset).ok())
+                .and_then(|offset| counts.get(offset))
+                .map_or(0, |count| *count as usize),
+            Counts::Sparse(counts) => counts.get(&value).copied().unwrap_or(0),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::rng::Rng;
+
+    fn random_values(rng: &mut Rng, len: usize, spread: u64) -> Vec<i64> {
+        (0..len)
+            .map(|_| ((rng.next() % spread) as i64).wrapping_sub((spread / 2) as i64))
+            .collect()
+    }
+
+    #[test]
+    fn sorts_like_std() {
+        let mut rng = Rng(50);
+        for spread in [
+            1,
+            10,
+            1000,
+            100_000,
+            DENSE_SPAN + 1,
+            1 << 32,
+            1 << 40,
+            u64::MAX,
+        ] {
+            for len in [0, 1, 2, 100, RADIX_MIN_LEN] {
+                let mut values = random_values(&mut rng, len, spread);
+                let mut expected = values.clone();
+                expected.sort_unstable();
+                sort(&mut values);
+                assert_eq!(values, expected, "{len} values spread over {spread}");
+            }
+        }
+
+        let mut extremes = vec![i64::MAX, 0, i64::MIN, -1, i64::MIN, 1];
+        sort(&mut extremes);
+        assert_eq!(extremes, [i64::MIN, i64::MIN, -1, 0, 1, i64::MAX]);
+    }
+
+    #[test]
+    fn counts_like_a_hash_map() {
+        let mut rng = Rng(51);
+        for spread in [10, DENSE_SPAN * 4] {
+            let values = random_values(&mut rng, 2000, spread);
+            let expected = values.iter().copied().counts();
+            let counts = Counts::new(&values);
+            assert_eq!(matches!(counts, Counts::Dense { .. }), spread < DENSE_SPAN);
+            for value in random_values(&mut rng, 2000, spread * 2) {
+                assert_eq!(
+                    counts.get(value),
+                    expected.get(&value).copied().unwrap_or(0)
+                );
+            }
+        }
+        assert!(matches!(Counts::new(&[0, 1_000_000]), Counts::Sparse(_)));
+        assert_eq!(Counts::new(&[i64::MAX]).get(i64::MIN), 0);
+        assert_eq!(Counts::new(&[i64::MIN]).get(i64::MAX), 0);
+    }
+}
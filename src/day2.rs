@@ -1,11 +1,17 @@
 use itertools::Itertools;
+use levels::{Levels, MAX_LEVELS};
+use policy::Policy;
 use rayon::{iter::ParallelIterator, str::ParallelString};
 
+pub mod explain;
+pub mod levels;
+pub mod policy;
+
 // returns true if the line is valid, false if not
 // valid if all of the following:
 //  - either ascending or decending
 //  - the differences between each sequential number is > 0 and < 4
-fn line_is_valid(line: &str, safeties: usize) -> bool {
+pub fn line_is_valid(line: &str, safeties: usize) -> bool {
     let num_nums = line.split_ascii_whitespace().count();
     line.split_ascii_whitespace()
         .map(|num| num.parse::<usize>().unwrap())
@@ -22,16 +28,129 @@ fn line_is_valid(line: &str, safeties: usize) -> bool {
         })
 }
 
+pub fn parse_levels(line: &str) -> Vec<i64> {
+    line.split_ascii_whitespace()
+        .map(|num| num.parse().unwrap())
+        .collect()
+}
+
+/// Whether the levels can be made safe by removing at most `policy.removals` of them. A kept
+/// level is only ever next to one of the `removals + 1` levels before it, so this takes
+/// O(levels * removals) rather than trying every combination, and doesn't allocate unless more
+/// than `MAX_LEVELS` removals are allowed.
+pub fn is_safe(levels: &[i64], policy: &Policy) -> bool {
+    if policy.removals >= MAX_LEVELS {
+        return removals(levels, policy).is_some();
+    }
+    policy
+        .ascending()
+        .iter()
+        .any(|&ascending| fewest_removals(levels, policy, ascending) <= policy.removals)
+}
+
+/// Whether a line of the input is a safe report, parsed and checked without allocating unless
+/// it has more than `MAX_LEVELS` levels. Lines the stack parser turns down go through
+/// `parse_levels`, so both read the same reports.
+pub fn report_is_safe(line: &str, policy: &Policy) -> bool {
+    match Levels::parse(line.as_bytes()) {
+        Ok(levels) => is_safe(&levels, policy),
+        Err(_) => is_safe(&parse_levels(line), policy),
+    }
+}
+
+// `dampen` without working out which levels go. Only the counts for the last `removals + 1`
+// levels are ever looked at, so they're kept in a ring.
+fn fewest_removals(levels: &[i64], policy: &Policy, ascending: bool) -> usize {
+    let window = policy.removals + 1;
+    let mut ring = [0; MAX_LEVELS];
+    let mut fewest = 0;
+    for (i, &level) in levels.iter().enumerate() {
+        let removed = (i.saturating_sub(window)..i)
+            .filter(|&prev| policy.steps_well(levels[prev], level, ascending))
+            .map(|prev| ring[prev % window] + (i - prev - 1))
+            .fold(i, usize::min);
+        ring[i % window] = removed;
+        let total = removed + (levels.len() - 1 - i);
+        fewest = if i == 0 { total } else { fewest.min(total) };
+    }
+    fewest
+}
+
+/// The fewest levels, by index, that have to be removed to make the rest safe, if the policy
+/// allows removing that many
+pub fn removals(levels: &[i64], policy: &Policy) -> Option<Vec<usize>> {
+    policy
+        .ascending()
+        .iter()
+        .filter_map(|&ascending| dampen(levels, policy, ascending))
+        .min_by_key(Vec::len)
+}
+
+// the fewest levels to remove so the rest step in one direction
+fn dampen(levels: &[i64], policy: &Policy, ascending: bool) -> Option<Vec<usize>> {
+    // removals[i] is the fewest removals before level i that leave everything up to and
+    // including it stepping well, with level i kept, and previous[i] the level kept before it
+    let mut removals = Vec::with_capacity(levels.len());
+    let mut previous = Vec::with_capacity(levels.len());
+    for (i, &level) in levels.iter().enumerate() {
+        // ties go to the latest level, so the levels reported as removed come as early as they
+        // can
+        let (removed, prev) = (i.saturating_sub(policy.removals + 1)..i)
+            .rev()
+            .filter(|&prev| policy.steps_well(levels[prev], level, ascending))
+            .map(|prev| (removals[prev] + (i - prev - 1), Some(prev)))
+            .chain([(i, None)])
+            .min_by_key(|(removed, _)| *removed)
+            .unwrap();
+        removals.push(removed);
+        previous.push(prev);
+    }
+    let (removed, last) = removals
+        .iter()
+        .enumerate()
+        .rev()
+        .map(|(i, removed)| (removed + (levels.len() - 1 - i), i))
+        .min_by_key(|(removed, _)| *removed)
+        .unwrap_or((0, 0));
+    if removed > policy.removals {
+        return None;
+    }
+
+    let mut kept = vec![false; levels.len()];
+    let mut cursor = Some(last).filter(|_| !levels.is_empty());
+    while let Some(i) = cursor {
+        kept[i] = true;
+        cursor = previous[i];
+    }
+    Some((0..levels.len()).filter(|&i| !kept[i]).collect())
+}
+
 #[aoc(day2, part1)]
 pub fn part1(input: &str) -> usize {
     input
         .par_lines()
-        .filter(|line| line_is_valid(line, 0))
+        .filter(|line| report_is_safe(line, &Policy::default()))
         .count()
 }
 
 #[aoc(day2, part2)]
 pub fn part2(input: &str) -> usize {
+    input
+        .par_lines()
+        .filter(|line| report_is_safe(line, &Policy::dampened()))
+        .count()
+}
+
+#[aoc(day2, part1, combinations)]
+pub fn part1_combinations(input: &str) -> usize {
+    input
+        .par_lines()
+        .filter(|line| line_is_valid(line, 0))
+        .count()
+}
+
+#[aoc(day2, part2, combinations)]
+pub fn part2_combinations(input: &str) -> usize {
     input
         .par_lines()
         .filter(|line| line_is_valid(line, 1))
@@ -48,6 +167,7 @@ mod test {
 8 6 4 4 1
 1 3 6 7 9";
     use super::*;
+    use crate::rng::Rng;
     use std::fs;
 
     fn get_input() -> String {
@@ -74,4 +194,124 @@ mod test {
     fn part2_real_input() {
         assert_eq!(part2(&get_input()), 514)
     }
+
+    #[test]
+    fn dampener_matches_combinations_on_real_input() {
+        for line in get_input().lines() {
+            for safeties in 0..=3 {
+                let policy = Policy {
+                    removals: safeties,
+                    ..Policy::default()
+                };
+                assert_eq!(
+                    is_safe(&parse_levels(line), &policy),
+                    line_is_valid(line, safeties),
+                    "{line} with {safeties} safeties"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn dampener_matches_combinations_on_random_reports() {
+        let mut rng = Rng(2);
+        for _ in 0..5000 {
+            let len = 2 + rng.below(8);
+            let mut level = 50;
+            let line = (0..len)
+                .map(|_| {
+                    // mostly small steps in either direction, with the odd big jump
+                    level += rng.below(9) as i64 - 4;
+                    if rng.below(10) == 0 {
+                        level += 10;
+                    }
+                    level.to_string()
+                })
+                .join(" ");
+            for safeties in 0..len {
+                let policy = Policy {
+                    removals: safeties,
+                    ..Policy::default()
+                };
+                assert_eq!(
+                    is_safe(&parse_levels(&line), &policy),
+                    line_is_valid(&line, safeties),
+                    "{line} with {safeties} safeties"
+                );
+                assert_eq!(
+                    is_safe(&parse_levels(&line), &policy),
+                    removals(&parse_levels(&line), &policy).is_some(),
+                    "{line} with {safeties} safeties"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn removing_down_to_a_pair() {
+        let with_removals = |removals| Policy {
+            removals,
+            ..Policy::default()
+        };
+        assert!(is_safe(&[1, 9, 2, 9], &with_removals(3)));
+        assert!(is_safe(&[1, 9, 2, 9], &with_removals(2)));
+        assert!(!is_safe(&[1, 9, 2, 9], &with_removals(1)));
+        assert!(is_safe(&[], &with_removals(0)));
+    }
+
+    #[test]
+    fn other_policies() {
+        use policy::{Direction, Monotonicity};
+        let plateaus = Policy {
+            monotonicity: Monotonicity::NonStrict,
+            ..Policy::default()
+        };
+        assert!(is_safe(&[8, 6, 4, 4, 1], &plateaus));
+        assert!(!is_safe(&[8, 6, 4, 4, 1], &Policy::default()));
+
+        let ascending = Policy {
+            direction: Direction::Ascending,
+            ..Policy::default()
+        };
+        assert!(is_safe(&[1, 3, 6, 7, 9], &ascending));
+        assert!(!is_safe(&[7, 6, 4, 2, 1], &ascending));
+
+        let big_steps = Policy {
+            min_step: 4,
+            max_step: 6,
+            removals: 1,
+            ..Policy::default()
+        };
+        assert_eq!(removals(&[1, 6, 8, 11], &big_steps), Some(vec![2]));
+        assert_eq!(removals(&[1, 2, 3], &big_steps), None);
+    }
+
+    #[test]
+    fn reports_longer_than_the_stack_buffer() {
+        let ascending = (1..=40).join(" ");
+        let one_off = ascending.replace(" 20 ", " 99 ");
+        let input = format!("{ascending}\n{one_off}\n");
+        assert_eq!(part1(&input), 1);
+        assert_eq!(part2(&input), 2);
+        assert!(!report_is_safe(&one_off, &Policy::default()));
+        assert!(report_is_safe(&one_off, &Policy::dampened()));
+    }
+
+    #[test]
+    fn stack_and_heap_parsers_agree() {
+        for line in [
+            "+1 2 3",
+            "-9223372036854775808 -9223372036854775807",
+            "+7 +6 +4",
+        ] {
+            assert!(Levels::parse(line.as_bytes()).is_ok(), "{line}");
+            for policy in [Policy::default(), Policy::dampened()] {
+                assert_eq!(
+                    report_is_safe(line, &policy),
+                    is_safe(&parse_levels(line), &policy),
+                    "{line}"
+                );
+            }
+        }
+    }
 }
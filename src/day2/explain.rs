@@ -0,0 +1,185 @@
+use super::{parse_levels, policy::Policy, removals};
+use itertools::Itertools;
+use std::fmt::Display;
+
+/// What's wrong with a pair of adjacent levels
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Problem {
+    // the step is a size the policy doesn't allow
+    Step(i64),
+    // the levels turn around from the policy's direction, or the one set by the first pair
+    Direction,
+}
+
+/// The first adjacent pair of levels that isn't safe, starting at `index`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Break {
+    pub index: usize,
+    pub problem: Problem,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Diagnosis {
+    pub levels: Vec<i64>,
+    pub first_break: Option<Break>,
+    // the levels the dampener removed, or None when it couldn't make the report safe
+    pub removed: Option<Vec<usize>>,
+}
+
+impl Diagnosis {
+    pub fn new(levels: &[i64], policy: &Policy) -> Self {
+        Self {
+            levels: levels.to_vec(),
+            first_break: first_break(levels, policy),
+            removed: removals(levels, policy),
+        }
+    }
+
+    pub fn is_safe(&self) -> bool {
+        self.removed.is_some()
+    }
+
+    /// The diagnosis as a single line of JSON
+    pub fn to_json(&self) -> String {
+        let first_break = match self.first_break {
+            None => "null".to_string(),
+            Some(Break {
+                index,
+                problem: Problem::Step(step),
+            }) => format!(r#"{{"index":{index},"problem":"step","step":{step}}}"#),
+            Some(Break {
+                index,
+                problem: Problem::Direction,
+            }) => format!(r#"{{"index":{index},"problem":"direction"}}"#),
+        };
+        let removed = match &self.removed {
+            Some(removed) => format!("[{}]", removed.iter().join(",")),
+            None => "null".to_string(),
+        };
+        format!(
+            r#"{{"levels":[{}],"safe":{},"break":{first_break},"removed":{removed}}}"#,
+            self.levels.iter().join(","),
+            self.is_safe(),
+        )
+    }
+}
+
+fn first_break(levels: &[i64], policy: &Policy) -> Option<Break> {
+    let ascending = match policy.ascending() {
+        [ascending] => *ascending,
+        _ => levels.get(1)? > levels.first()?,
+    };
+    levels
+        .iter()
+        .tuple_windows()
+        .map(|(prev, curr)| curr - prev)
+        .enumerate()
+        .find_map(|(index, step)| {
+            let directed = if ascending { step } else { -step };
+            let problem = if directed < 0 {
+                Problem::Direction
+            } else if !policy.allows(directed) {
+                Problem::Step(step)
+            } else {
+                return None;
+            };
+            Some(Break { index, problem })
+        })
+}
+
+/// Diagnoses every report in the input, in order
+pub fn diagnose(input: &str, policy: &Policy) -> Vec<Diagnosis> {
+    input
+        .lines()
+        .map(|line| Diagnosis::new(&parse_levels(line), policy))
+        .collect()
+}
+
+impl Display for Diagnosis {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let verdict = match &self.removed {
+            None => "unsafe",
+            Some(removed) if removed.is_empty() => "safe",
+            Some(_) => "dampened",
+        };
+        write!(f, "{:<24}  {verdict}", self.levels.iter().join(" "))?;
+        if let Some(Break { index, problem }) = self.first_break {
+            let (prev, curr) = (self.levels[index], self.levels[index + 1]);
+            match problem {
+                Problem::Step(step) => write!(f, ", {prev} -> {curr} steps by {step}")?,
+                Problem::Direction => write!(f, ", {prev} -> {curr} turns around")?,
+            }
+            write!(f, " at levels {}..={}", index, index + 1)?;
+        }
+        match &self.removed {
+            Some(removed) if !removed.is_empty() => {
+                write!(f, ", removed level {}", removed.iter().join(", "))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const SAMPLE_INPUT: &str = "7 6 4 2 1
+1 2 7 8 9
+9 7 6 2 1
+1 3 2 4 5
+8 6 4 4 1
+1 3 6 7 9";
+
+    #[test]
+    fn explains_sample() {
+        let lines = diagnose(SAMPLE_INPUT, &Policy::dampened())
+            .iter()
+            .map(|diagnosis| diagnosis.to_string())
+            .collect_vec();
+        assert_eq!(
+            lines,
+            [
+                "7 6 4 2 1                 safe",
+                "1 2 7 8 9                 unsafe, 2 -> 7 steps by 5 at levels 1..=2",
+                "9 7 6 2 1                 unsafe, 6 -> 2 steps by -4 at levels 2..=3",
+                "1 3 2 4 5                 dampened, 3 -> 2 turns around at levels 1..=2, removed level 1",
+                "8 6 4 4 1                 dampened, 4 -> 4 steps by 0 at levels 2..=3, removed level 2",
+                "1 3 6 7 9                 safe",
+            ]
+        );
+    }
+
+    #[test]
+    fn json() {
+        let diagnoses = diagnose("1 3 2 4 5\n1 2 7 8 9\n1 2", &Policy::dampened());
+        assert_eq!(
+            diagnoses.iter().map(Diagnosis::to_json).collect_vec(),
+            [
+                r#"{"levels":[1,3,2,4,5],"safe":true,"break":{"index":1,"problem":"direction"},"removed":[1]}"#,
+                r#"{"levels":[1,2,7,8,9],"safe":false,"break":{"index":1,"problem":"step","step":5},"removed":null}"#,
+                r#"{"levels":[1,2],"safe":true,"break":null,"removed":[]}"#,
+            ]
+        );
+    }
+
+    #[test]
+    fn breaks_follow_the_policy() {
+        let descending = "direction=descending,max=4".parse().unwrap();
+        assert_eq!(
+            Diagnosis::new(&[9, 5, 6, 2], &descending).first_break,
+            Some(Break {
+                index: 1,
+                problem: Problem::Direction
+            })
+        );
+        assert_eq!(
+            Diagnosis::new(&[1, 2], &descending).first_break,
+            Some(Break {
+                index: 0,
+                problem: Problem::Direction
+            })
+        );
+        assert_eq!(Diagnosis::new(&[9, 5, 4, 0], &descending).first_break, None);
+    }
+}
@@ -0,0 +1,125 @@
+use std::{error::Error, fmt::Display, ops::Deref};
+
+/// The most levels a report can have, so that it fits on the stack
+pub const MAX_LEVELS: usize = 32;
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum LevelsError {
+    TooManyLevels,
+    // the level starting at `column` isn't a number that fits in an i64
+    InvalidLevel { column: usize },
+}
+
+impl Display for LevelsError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LevelsError::TooManyLevels => {
+                write!(f, "reports can't have more than {MAX_LEVELS} levels")
+            }
+            LevelsError::InvalidLevel { column } => write!(f, "invalid level at column {column}"),
+        }
+    }
+}
+
+impl Error for LevelsError {}
+
+/// The levels of a report, read straight from the bytes of a line without allocating
+#[derive(Clone, Copy, Debug)]
+pub struct Levels {
+    levels: [i64; MAX_LEVELS],
+    len: usize,
+}
+
+impl Levels {
+    pub fn parse(line: &[u8]) -> Result<Self, LevelsError> {
+        let mut levels = [0; MAX_LEVELS];
+        let mut len = 0;
+        let mut pos = 0;
+        while pos < line.len() {
+            if line[pos].is_ascii_whitespace() {
+                pos += 1;
+                continue;
+            }
+            let start = pos;
+            let invalid = LevelsError::InvalidLevel { column: start };
+            // the same signs as `str::parse`, and negative levels are built downwards so i64::MIN
+            // still fits
+            let negative = line[pos] == b'-';
+            pos += matches!(line[pos], b'-' | b'+') as usize;
+            let digits = pos;
+            let mut level: i64 = 0;
+            while pos < line.len() && !line[pos].is_ascii_whitespace() {
+                let digit = line[pos].wrapping_sub(b'0');
+                if digit > 9 {
+                    return Err(invalid);
+                }
+                level = level
+                    .checked_mul(10)
+                    .and_then(|level| {
+                        if negative {
+                            level.checked_sub(digit as i64)
+                        } else {
+                            level.checked_add(digit as i64)
+                        }
+                    })
+                    .ok_or(LevelsError::InvalidLevel { column: start })?;
+                pos += 1;
+            }
+            if pos == digits {
+                return Err(invalid);
+            }
+            if len == MAX_LEVELS {
+                return Err(LevelsError::TooManyLevels);
+            }
+            levels[len] = level;
+            len += 1;
+        }
+        Ok(Self { levels, len })
+    }
+}
+
+impl Deref for Levels {
+    type Target = [i64];
+
+    fn deref(&self) -> &Self::Target {
+        &self.levels[..self.len]
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parse() {
+        assert_eq!(*Levels::parse(b"7 6 4 2 1").unwrap(), [7, 6, 4, 2, 1]);
+        assert_eq!(*Levels::parse(b"  10\t-2  0 \r").unwrap(), [10, -2, 0]);
+        assert_eq!(*Levels::parse(b"").unwrap(), []);
+        assert_eq!(*Levels::parse(b"+1 -0 +0").unwrap(), [1, 0, 0]);
+        assert_eq!(
+            *Levels::parse(b"-9223372036854775808 9223372036854775807").unwrap(),
+            [i64::MIN, i64::MAX]
+        );
+        assert_eq!(
+            Levels::parse(b"1 2x 3").unwrap_err(),
+            LevelsError::InvalidLevel { column: 2 }
+        );
+        assert_eq!(
+            Levels::parse(b"1 - 3").unwrap_err(),
+            LevelsError::InvalidLevel { column: 2 }
+        );
+        assert_eq!(
+            Levels::parse(b"1 +").unwrap_err(),
+            LevelsError::InvalidLevel { column: 2 }
+        );
+        assert_eq!(
+            Levels::parse(b"9223372036854775808").unwrap_err(),
+            LevelsError::InvalidLevel { column: 0 }
+        );
+        let long = "1 ".repeat(MAX_LEVELS + 1);
+        assert_eq!(
+            Levels::parse(long.as_bytes()).unwrap_err(),
+            LevelsError::TooManyLevels
+        );
+    }
+}
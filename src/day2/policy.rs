@@ -0,0 +1,171 @@
+use std::{error::Error, fmt::Display, num::ParseIntError, str::FromStr};
+
+/// Which way the levels of a report have to go
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Direction {
+    #[default]
+    Either,
+    Ascending,
+    Descending,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Monotonicity {
+    // every step has to change the level
+    #[default]
+    Strict,
+    // a level may repeat the one before it
+    NonStrict,
+}
+
+/// What makes a report safe. The default is the puzzle's rules without the Problem Dampener.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Policy {
+    pub min_step: i64,
+    pub max_step: i64,
+    pub monotonicity: Monotonicity,
+    // how many levels the Problem Dampener may remove
+    pub removals: usize,
+    pub direction: Direction,
+}
+
+impl Default for Policy {
+    fn default() -> Self {
+        Self {
+            min_step: 1,
+            max_step: 3,
+            monotonicity: Monotonicity::Strict,
+            removals: 0,
+            direction: Direction::Either,
+        }
+    }
+}
+
+impl Policy {
+    /// The puzzle's rules with the Problem Dampener
+    pub fn dampened() -> Self {
+        Self {
+            removals: 1,
+            ..Self::default()
+        }
+    }
+
+    /// The directions to try, as whether the levels ascend
+    pub fn ascending(&self) -> &'static [bool] {
+        match self.direction {
+            Direction::Either => &[true, false],
+            Direction::Ascending => &[true],
+            Direction::Descending => &[false],
+        }
+    }
+
+    /// Whether a step, measured in the direction the report is going, is allowed
+    pub fn allows(&self, step: i64) -> bool {
+        (step == 0 && self.monotonicity == Monotonicity::NonStrict)
+            || (self.min_step..=self.max_step).contains(&step)
+    }
+
+    pub fn steps_well(&self, prev: i64, curr: i64, ascending: bool) -> bool {
+        self.allows(if ascending { curr - prev } else { prev - curr })
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum PolicyParseError {
+    UnknownSetting(String),
+    InvalidValue { setting: String, value: String },
+}
+
+impl Display for PolicyParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PolicyParseError::UnknownSetting(setting) => {
+                write!(f, "unknown policy setting {setting:?}")
+            }
+            PolicyParseError::InvalidValue { setting, value } => {
+                write!(f, "{value:?} isn't a valid {setting}")
+            }
+        }
+    }
+}
+
+impl Error for PolicyParseError {}
+
+/// Reads comma separated settings, like `removals=1,direction=ascending`, on top of the default
+/// policy. The settings are `min`, `max`, `monotonic` (`strict` or `non-strict`), `removals` and
+/// `direction` (`either`, `ascending` or `descending`).
+impl FromStr for Policy {
+    type Err = PolicyParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut policy = Policy::default();
+        for setting in s.split(',').filter(|setting| !setting.is_empty()) {
+            let (name, value) = setting
+                .split_once('=')
+                .ok_or_else(|| PolicyParseError::UnknownSetting(setting.to_string()))?;
+            let invalid = || PolicyParseError::InvalidValue {
+                setting: name.to_string(),
+                value: value.to_string(),
+            };
+            let number = |_: ParseIntError| invalid();
+            match name {
+                "min" => policy.min_step = value.parse().map_err(number)?,
+                "max" => policy.max_step = value.parse().map_err(number)?,
+                "removals" => policy.removals = value.parse().map_err(number)?,
+                "monotonic" => {
+                    policy.monotonicity = match value {
+                        "strict" => Monotonicity::Strict,
+                        "non-strict" => Monotonicity::NonStrict,
+                        _ => return Err(invalid()),
+                    }
+                }
+                "direction" => {
+                    policy.direction = match value {
+                        "either" => Direction::Either,
+                        "ascending" => Direction::Ascending,
+                        "descending" => Direction::Descending,
+                        _ => return Err(invalid()),
+                    }
+                }
+                _ => return Err(PolicyParseError::UnknownSetting(name.to_string())),
+            }
+        }
+        Ok(policy)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parse() {
+        assert_eq!("".parse(), Ok(Policy::default()));
+        assert_eq!("removals=1".parse(), Ok(Policy::dampened()));
+        assert_eq!(
+            "min=0,max=5,monotonic=non-strict,removals=2,direction=descending".parse(),
+            Ok(Policy {
+                min_step: 0,
+                max_step: 5,
+                monotonicity: Monotonicity::NonStrict,
+                removals: 2,
+                direction: Direction::Descending,
+            })
+        );
+        assert_eq!(
+            "removals=-1".parse::<Policy>(),
+            Err(PolicyParseError::InvalidValue {
+                setting: "removals".to_string(),
+                value: "-1".to_string()
+            })
+        );
+        assert_eq!(
+            "step=2".parse::<Policy>(),
+            Err(PolicyParseError::UnknownSetting("step".to_string()))
+        );
+        assert_eq!(
+            "strict".parse::<Policy>(),
+            Err(PolicyParseError::UnknownSetting("strict".to_string()))
+        );
+    }
+}
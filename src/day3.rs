@@ -1,3 +1,12 @@
+use lexer::{Grammar, Instruction, Lexer, Token};
+use std::{error::Error, fmt::Display};
+
+pub mod generate;
+pub mod interpreter;
+pub mod lexer;
+pub mod stream;
+pub mod trace;
+
 #[inline]
 fn parse_digit(byte: u8, digit: &mut [u8], digit_len: &mut usize) -> bool {
     let is_digit = byte.is_ascii_digit();
@@ -13,7 +22,7 @@ fn parse_digit(byte: u8, digit: &mut [u8], digit_len: &mut usize) -> bool {
 }
 
 #[inline]
-fn read_memory(input: &str, do_do: bool) -> usize {
+pub fn read_memory(input: &str, do_do: bool) -> usize {
     let mut x = [0, 0, 0];
     let mut y = [0, 0, 0];
     let mut x_len = 0;
@@ -101,17 +110,13 @@ fn read_memory(input: &str, do_do: bool) -> usize {
                         reading_dos = false;
                     }
                 }
-                b'o' => {
-                    if byte != b'n' && reading_dos {
-                        reading_dos = false;
-                        mul_enabled = true;
-                    }
+                b'o' if byte != b'n' && reading_dos => {
+                    reading_dos = false;
+                    mul_enabled = true;
                 }
-                b'n' => {
-                    if byte != b'\'' && reading_dos {
-                        reading_dos = false;
-                        mul_enabled = true;
-                    }
+                b'n' if byte != b'\'' && reading_dos => {
+                    reading_dos = false;
+                    mul_enabled = true;
                 }
                 b'\'' => {
                     mul_enabled = if byte == b't' && reading_dos {
@@ -131,28 +136,161 @@ fn read_memory(input: &str, do_do: bool) -> usize {
             y_len = 0;
             x_len = 0;
         }
-        //println!(
-        //    "byte: {}, parsing dos: {}, mul_en: {}",
-        //    byte as char, reading_dos, mul_enabled
-        //);
         prev = byte;
     });
     sum
 }
 
+/// How the products are added up
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Accumulator {
+    // every product and running total has to fit in an i64
+    #[default]
+    Checked64,
+    Wide128,
+}
+
+impl Accumulator {
+    fn fits(self, value: i128) -> bool {
+        match self {
+            Accumulator::Checked64 => i64::try_from(value).is_ok(),
+            Accumulator::Wide128 => true,
+        }
+    }
+
+    /// `total` with `value` added, if the value and the new total both fit. A value of `None`
+    /// already overflowed while it was being worked out.
+    pub fn add(self, total: i128, value: Option<i128>) -> Option<i128> {
+        value
+            .filter(|value| self.fits(*value))
+            .and_then(|value| total.checked_add(value))
+            .filter(|total| self.fits(*total))
+    }
+}
+
+/// A mul instruction whose product, or the total after adding it, didn't fit the accumulator
+#[derive(Debug, PartialEq, Eq)]
+pub struct OverflowError {
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Display for OverflowError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "the mul at bytes {}..{} overflowed the accumulator",
+            self.start, self.end
+        )
+    }
+}
+
+impl Error for OverflowError {}
+
+/// Adds up the products of mul instructions as they are fed in. When `do_do` is set, do() and
+/// don't() turn the instructions after them on and off.
+pub struct Evaluator {
+    do_do: bool,
+    accumulator: Accumulator,
+    mul_enabled: bool,
+    total: i128,
+}
+
+impl Evaluator {
+    pub fn new(do_do: bool, accumulator: Accumulator) -> Self {
+        Self {
+            do_do,
+            accumulator,
+            mul_enabled: true,
+            total: 0,
+        }
+    }
+
+    pub fn feed(&mut self, token: &Token) -> Result<(), OverflowError> {
+        match token.instruction {
+            Instruction::Mul(x, y) if self.mul_enabled || !self.do_do => {
+                self.total =
+                    self.accumulator
+                        .add(self.total, x.checked_mul(y))
+                        .ok_or(OverflowError {
+                            start: token.start,
+                            end: token.end,
+                        })?;
+            }
+            Instruction::Mul(..) => {}
+            Instruction::Do => self.mul_enabled = true,
+            Instruction::Dont => self.mul_enabled = false,
+        }
+        Ok(())
+    }
+
+    pub fn mul_enabled(&self) -> bool {
+        self.mul_enabled || !self.do_do
+    }
+
+    pub fn total(&self) -> i128 {
+        self.total
+    }
+}
+
+pub fn evaluate(
+    tokens: impl Iterator<Item = Token>,
+    do_do: bool,
+    accumulator: Accumulator,
+) -> Result<i128, OverflowError> {
+    let mut evaluator = Evaluator::new(do_do, accumulator);
+    tokens
+        .into_iter()
+        .try_for_each(|token| evaluator.feed(&token))?;
+    Ok(evaluator.total())
+}
+
 #[aoc(day3, part1)]
-fn part1(input: &str) -> usize {
-    read_memory(input, false)
+fn part1(input: &str) -> Result<i128, OverflowError> {
+    evaluate(Lexer::new(input), false, Accumulator::Checked64)
 }
 
 #[aoc(day3, part2)]
-fn part2(input: &str) -> usize {
+fn part2(input: &str) -> Result<i128, OverflowError> {
+    evaluate(Lexer::new(input), true, Accumulator::Checked64)
+}
+
+// part2 with the state machine's reading of do and don't
+#[aoc(day3, part2, loose)]
+fn part2_loose(input: &str) -> Result<i128, OverflowError> {
+    evaluate(
+        Lexer::with_grammar(input, Grammar::Loose),
+        true,
+        Accumulator::Checked64,
+    )
+}
+
+#[aoc(day3, part1, interpreter)]
+fn part1_interpreter(input: &str) -> Result<i128, OverflowError> {
+    let evaluator = Evaluator::new(false, Accumulator::Checked64);
+    Ok(interpreter::standard().run(input, evaluator)?.total())
+}
+
+#[aoc(day3, part2, interpreter)]
+fn part2_interpreter(input: &str) -> Result<i128, OverflowError> {
+    let evaluator = Evaluator::new(true, Accumulator::Checked64);
+    Ok(interpreter::standard().run(input, evaluator)?.total())
+}
+
+#[aoc(day3, part1, state_machine)]
+fn part1_state_machine(input: &str) -> usize {
+    read_memory(input, false)
+}
+
+#[aoc(day3, part2, state_machine)]
+fn part2_state_machine(input: &str) -> usize {
     read_memory(input, true)
 }
 
 #[cfg(test)]
 mod test {
     use super::*;
+    use lexer::{Operands, Signs};
     use std::fs;
 
     const SAMPLE_INPUT: &str =
@@ -166,21 +304,91 @@ mod test {
 
     #[test]
     fn part1_sample_input() {
-        assert_eq!(part1(SAMPLE_INPUT), 161)
+        assert_eq!(part1(SAMPLE_INPUT), Ok(161))
     }
 
     #[test]
     fn part1_real_input() {
-        assert_eq!(part1(&get_input()), 170778545)
+        assert_eq!(part1(&get_input()), Ok(170778545))
     }
 
     #[test]
     fn part2_sample_input() {
-        assert_eq!(part2(SAMPLE_INPUT_2), 48)
+        assert_eq!(part2(SAMPLE_INPUT_2), Ok(48))
     }
 
     #[test]
     fn part2_real_input() {
-        assert_eq!(part2(&get_input()), 82868252)
+        assert_eq!(part2(&get_input()), Ok(82868252))
+    }
+
+    #[test]
+    fn interpreter_real_input() {
+        let input = get_input();
+        assert_eq!(part1_interpreter(&input), Ok(170778545));
+        assert_eq!(part2_interpreter(&input), Ok(82868252));
+    }
+
+    #[test]
+    fn loose_grammar_matches_state_machine() {
+        for input in [
+            SAMPLE_INPUT_2,
+            "don't()mul(1,1)dox mul(2,2)",
+            "don't()mul(1,1)done mul(2,2)",
+            "don't()mul(1,1)don'x mul(2,2)don'tmul(3,3)",
+            "do()don'tmul(4,4)do(mul(5,5)",
+        ] {
+            assert_eq!(
+                part2_loose(input),
+                Ok(part2_state_machine(input) as i128),
+                "{input}"
+            );
+        }
+        assert_eq!(part2(&get_input()), part2_loose(&get_input()));
+        assert_eq!(part2("don't()mul(1,1)dox mul(2,2)"), Ok(0));
+    }
+
+    #[test]
+    fn accumulator_overflow() {
+        let operands = Operands {
+            max_digits: 10,
+            signs: Signs::Minus,
+        };
+        let input = "mul(-3,4)mul(3037000500,3037000500)";
+        let tokens = || Lexer::new(input).with_operands(operands);
+        assert_eq!(
+            evaluate(tokens(), false, Accumulator::Checked64),
+            Err(OverflowError { start: 9, end: 35 })
+        );
+        assert_eq!(
+            evaluate(tokens(), false, Accumulator::Wide128),
+            Ok(3037000500 * 3037000500 - 12)
+        );
+    }
+
+    #[test]
+    fn lexer_matches_interpreter_on_generated_memory() {
+        for seed in 0..5 {
+            let memory = generate::generate(200_000, seed);
+            for do_do in [false, true] {
+                assert_eq!(
+                    evaluate(Lexer::new(&memory), do_do, Accumulator::Checked64),
+                    interpreter::standard()
+                        .run(&memory, Evaluator::new(do_do, Accumulator::Checked64))
+                        .map(|evaluator| evaluator.total())
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn state_machine_matches_lexer() {
+        let input = get_input();
+        assert_eq!(Ok(part1_state_machine(&input) as i128), part1(&input));
+        assert_eq!(
+            Ok(part2_state_machine(SAMPLE_INPUT_2) as i128),
+            part2(SAMPLE_INPUT_2)
+        );
+        assert_eq!(Ok(part2_state_machine(&input) as i128), part2(&input));
     }
 }
@@ -0,0 +1,77 @@
+use crate::rng::Rng;
+
+// fragments of instructions and the kind of junk found around them in the puzzle input
+const NOISE: &[&str] = &[
+    "mul(", "mul[", "mul (", "do", "don't", "do(", "don't(", ")", "(", ",", "]", "'", " ", "*",
+    "%", "&", "@", "^", "!", "?", "<", ">", "{", "}", "how()", "select()", "from()", "what()",
+    "why()", "who()", "where()", "when()", "mul(4*", "mul(6,9!", "?(12,34)", "m", "u", "l",
+];
+
+/// Corrupted memory of exactly `len` bytes. About a third of it is valid instructions, and the
+/// rest is noise that often looks almost like one.
+pub fn generate(len: usize, seed: u64) -> String {
+    build(len, seed, "")
+}
+
+/// Like `generate`, but with a space between every fragment. The original state machine can't
+/// restart an instruction in the middle of another, like in `mmul(1,2)`, so this is the memory it
+/// reads the same way as the loose lexer.
+pub fn generate_spaced(len: usize, seed: u64) -> String {
+    build(len, seed, " ")
+}
+
+fn build(len: usize, seed: u64, separator: &str) -> String {
+    let mut rng = Rng(seed);
+    let mut memory = String::with_capacity(len + 16);
+    while memory.len() < len {
+        memory.push_str(separator);
+        match rng.below(20) {
+            0..=5 => {
+                let (x, y) = (rng.below(1000), rng.below(1000));
+                memory.push_str(&format!("mul({x},{y})"));
+            }
+            6 => memory.push_str("do()"),
+            7 => memory.push_str("don't()"),
+            _ => memory.push_str(NOISE[rng.below(NOISE.len())]),
+        }
+    }
+    memory.truncate(len);
+    memory
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::day3::{
+        evaluate,
+        lexer::{Grammar, Lexer},
+        read_memory, Accumulator,
+    };
+
+    #[test]
+    fn exact_length_and_deterministic() {
+        let memory = generate(10_000, 3);
+        assert_eq!(memory.len(), 10_000);
+        assert_eq!(memory, generate(10_000, 3));
+        assert_ne!(memory, generate(10_000, 4));
+        assert_eq!(generate_spaced(10_000, 3).len(), 10_000);
+    }
+
+    #[test]
+    fn spaced_memory_reads_the_same_in_the_state_machine() {
+        for seed in 0..20 {
+            let memory = generate_spaced(100_000, seed);
+            for do_do in [false, true] {
+                assert_eq!(
+                    evaluate(
+                        Lexer::with_grammar(&memory, Grammar::Loose),
+                        do_do,
+                        Accumulator::Checked64
+                    ),
+                    Ok(read_memory(&memory, do_do) as i128),
+                    "seed {seed}"
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,229 @@
+use super::{
+    lexer::{Call, CallLexer, Instruction, Operands, Signature, Token},
+    Evaluator, OverflowError,
+};
+use std::convert::Infallible;
+
+type Handler<S, E> = Box<dyn Fn(&mut S, &Call) -> Result<(), E>>;
+
+impl Call {
+    /// The error for a call whose result didn't fit the accumulator
+    pub fn overflow(&self) -> OverflowError {
+        OverflowError {
+            start: self.start,
+            end: self.end,
+        }
+    }
+}
+
+/// Runs programs hidden in corrupted memory. Instructions look like `name(1,22,333)`, and are
+/// added by registering a name, the number of arguments it takes, and a handler that updates
+/// the interpreter state `S`, or stops the program with an error `E`.
+pub struct Interpreter<S, E = Infallible> {
+    // handlers[i] runs the calls lexed with signatures[i]
+    signatures: Vec<Signature>,
+    handlers: Vec<Handler<S, E>>,
+    operands: Operands,
+}
+
+impl<S, E> Default for Interpreter<S, E> {
+    fn default() -> Self {
+        Self {
+            signatures: Vec::new(),
+            handlers: Vec::new(),
+            operands: Operands::default(),
+        }
+    }
+}
+
+impl<S, E> Interpreter<S, E> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_operands(self, operands: Operands) -> Self {
+        Self { operands, ..self }
+    }
+
+    pub fn register(
+        mut self,
+        name: &str,
+        arity: usize,
+        handler: impl Fn(&mut S, &Call) -> Result<(), E> + 'static,
+    ) -> Self {
+        self.signatures.push(Signature {
+            name: name.to_string(),
+            arity,
+        });
+        self.handlers.push(Box::new(handler));
+        self
+    }
+
+    pub fn name(&self, call: &Call) -> &str {
+        &self.signatures[call.signature].name
+    }
+
+    /// Every registered instruction in `input`, in order
+    pub fn calls<'a>(&'a self, input: &'a str) -> CallLexer<'a> {
+        CallLexer::new(input, &self.signatures).with_operands(self.operands)
+    }
+
+    pub fn execute(&self, state: &mut S, call: &Call) -> Result<(), E> {
+        (self.handlers[call.signature])(state, call)
+    }
+
+    pub fn run(&self, input: &str, mut state: S) -> Result<S, E> {
+        self.calls(input)
+            .try_for_each(|call| self.execute(&mut state, &call))?;
+        Ok(state)
+    }
+}
+
+// runs one of the puzzle's own instructions the same way as when it's lexed as a token
+fn feed(
+    evaluator: &mut Evaluator,
+    call: &Call,
+    instruction: Instruction,
+) -> Result<(), OverflowError> {
+    evaluator.feed(&Token {
+        instruction,
+        start: call.start,
+        end: call.end,
+    })
+}
+
+/// The puzzle's instruction set, run by an `Evaluator`. Whether `do()` and `don't()` count, and
+/// how products are added up, is up to the evaluator the program is run with.
+pub fn standard() -> Interpreter<Evaluator, OverflowError> {
+    Interpreter::new()
+        .register("mul", 2, |evaluator: &mut Evaluator, call| {
+            feed(
+                evaluator,
+                call,
+                Instruction::Mul(call.args[0], call.args[1]),
+            )
+        })
+        .register("do", 0, |evaluator, call| {
+            feed(evaluator, call, Instruction::Do)
+        })
+        .register("don't", 0, |evaluator, call| {
+            feed(evaluator, call, Instruction::Dont)
+        })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::day3::{lexer::Signs, Accumulator};
+
+    const SAMPLE_INPUT: &str =
+        "xmul(2,4)&mul[3,7]!^don't()_mul(5,5)+mul(32,64](mul(11,8)undo()?mul(8,5))";
+
+    fn run(interpreter: &Interpreter<Evaluator, OverflowError>, input: &str, do_do: bool) -> i128 {
+        let evaluator = Evaluator::new(do_do, Accumulator::Checked64);
+        interpreter.run(input, evaluator).unwrap().total()
+    }
+
+    #[test]
+    fn standard_programs() {
+        assert_eq!(run(&standard(), SAMPLE_INPUT, false), 161);
+        assert_eq!(run(&standard(), SAMPLE_INPUT, true), 48);
+        assert_eq!(
+            standard()
+                .calls(SAMPLE_INPUT)
+                .map(|call| (call.start, call.end))
+                .collect::<Vec<_>>(),
+            [(1, 9), (20, 27), (28, 36), (48, 57), (59, 63), (64, 72)]
+        );
+    }
+
+    #[test]
+    fn overflow_stops_the_program() {
+        let wide = Operands {
+            max_digits: 30,
+            signs: Signs::Unsigned,
+        };
+        let interpreter = standard().with_operands(wide);
+        let evaluator = |accumulator| Evaluator::new(false, accumulator);
+
+        let input = "mul(2,3)mul(9999999999999999999,9999999999999999999)";
+        assert_eq!(
+            interpreter
+                .run(input, evaluator(Accumulator::Checked64))
+                .err(),
+            Some(OverflowError { start: 8, end: 52 })
+        );
+        assert_eq!(
+            interpreter
+                .run(input, evaluator(Accumulator::Wide128))
+                .unwrap()
+                .total(),
+            6 + 9999999999999999999 * 9999999999999999999
+        );
+
+        // too big for an i128 as well
+        let input = "mul(2,3)mul(99999999999999999999,99999999999999999999)";
+        assert_eq!(
+            interpreter
+                .run(input, evaluator(Accumulator::Wide128))
+                .err(),
+            Some(OverflowError { start: 8, end: 54 })
+        );
+    }
+
+    #[test]
+    fn custom_instructions() {
+        // an extra operator, and different keywords to switch it on and off
+        let interpreter = standard()
+            .register("sq", 1, |evaluator, call| {
+                feed(
+                    evaluator,
+                    call,
+                    Instruction::Mul(call.args[0], call.args[0]),
+                )
+            })
+            .register("on", 0, |evaluator, call| {
+                feed(evaluator, call, Instruction::Do)
+            })
+            .register("off", 0, |evaluator, call| {
+                feed(evaluator, call, Instruction::Dont)
+            });
+        assert_eq!(
+            run(&interpreter, "sq(3)off()mul(9,9)don't()on()mul(2,3)", true),
+            15
+        );
+    }
+
+    #[test]
+    fn conditional_blocks() {
+        // if(x) only runs the block up to end() when x is nonzero, and blocks can nest
+        #[derive(Default)]
+        struct Blocks {
+            skipping: Vec<bool>,
+            total: i128,
+        }
+        let interpreter = Interpreter::<Blocks, OverflowError>::new()
+            .register("mul", 2, |state, call| {
+                if !state.skipping.contains(&true) {
+                    let product = call.args[0].checked_mul(call.args[1]);
+                    state.total = Accumulator::Wide128
+                        .add(state.total, product)
+                        .ok_or(call.overflow())?;
+                }
+                Ok(())
+            })
+            .register("if", 1, |state, call| {
+                state.skipping.push(call.args[0] == 0);
+                Ok(())
+            })
+            .register("end", 0, |state, _| {
+                state.skipping.pop();
+                Ok(())
+            });
+        let state = interpreter.run(
+            "mul(1,1)if(0)mul(2,2)if(1)mul(3,3)end()end()if(7)mul(4,4)end()",
+            Blocks::default(),
+        );
+        assert_eq!(state.unwrap().total, 17);
+    }
+}
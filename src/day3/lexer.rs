@@ -0,0 +1,565 @@
+use memchr::memmem::Finder;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Instruction {
+    Mul(i128, i128),
+    Do,
+    Dont,
+}
+
+/// An instruction and the byte range of the input it was read from
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Token {
+    pub instruction: Instruction,
+    pub start: usize,
+    pub end: usize,
+}
+
+/// Something the lexer stopped at: either an instruction, or text that started out like one and
+/// then broke off
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Lexeme {
+    Token(Token),
+    NearMiss { start: usize, end: usize },
+}
+
+/// How closely `do()` and `don't()` have to be spelled out
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Grammar {
+    // only the literal `do()` and `don't()`
+    #[default]
+    Strict,
+    // what the original state machine accepted: `don't` disables, and `do` followed by anything
+    // that doesn't continue to `don't` enables, parentheses or not
+    Loose,
+}
+
+/// Which signs may be written in front of a number
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Signs {
+    #[default]
+    Unsigned,
+    Minus,
+    PlusMinus,
+}
+
+/// How the numbers inside an instruction may be written
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Operands {
+    pub max_digits: usize,
+    pub signs: Signs,
+}
+
+impl Default for Operands {
+    fn default() -> Self {
+        Self {
+            max_digits: 3,
+            signs: Signs::Unsigned,
+        }
+    }
+}
+
+impl Operands {
+    /// Reads a number starting at `pos`, returning it and the index after it. Numbers that
+    /// don't fit in an i128 aren't numbers.
+    pub fn parse(&self, input: &[u8], pos: usize) -> Option<(i128, usize)> {
+        let (negative, start) = match (self.signs, input.get(pos)?) {
+            (Signs::Minus | Signs::PlusMinus, b'-') => (true, pos + 1),
+            (Signs::PlusMinus, b'+') => (false, pos + 1),
+            _ => (false, pos),
+        };
+        let digits = input[start..]
+            .iter()
+            .take(self.max_digits)
+            .take_while(|byte| byte.is_ascii_digit())
+            .count();
+        if digits == 0 {
+            return None;
+        }
+        let number = input[start..start + digits]
+            .iter()
+            .try_fold(0i128, |num, byte| {
+                let digit = (byte - b'0') as i128;
+                match negative {
+                    true => num.checked_mul(10)?.checked_sub(digit),
+                    false => num.checked_mul(10)?.checked_add(digit),
+                }
+            })?;
+        Some((number, start + digits))
+    }
+}
+
+// Where the words that instructions start with come up next. Each word is only searched for
+// again once the lexer has moved past where it was last found.
+struct Candidates {
+    finders: Vec<Finder<'static>>,
+    // where each word next starts, once searched for, or the end of the input if it doesn't
+    next: Vec<Option<usize>>,
+}
+
+impl Candidates {
+    fn new<'w>(words: impl IntoIterator<Item = &'w [u8]>) -> Self {
+        let finders: Vec<_> = words
+            .into_iter()
+            .map(|word| Finder::new(word).into_owned())
+            .collect();
+        Self {
+            next: vec![None; finders.len()],
+            finders,
+        }
+    }
+
+    // the first index at or after `pos` where any of the words starts
+    fn next(&mut self, input: &[u8], pos: usize) -> Option<usize> {
+        let mut nearest = input.len();
+        for (finder, next) in self.finders.iter().zip(&mut self.next) {
+            let found = match *next {
+                Some(next) if next >= pos => next,
+                _ => finder
+                    .find(&input[pos.min(input.len())..])
+                    .map_or(input.len(), |at| pos + at),
+            };
+            *next = Some(found);
+            nearest = nearest.min(found);
+        }
+        Some(nearest).filter(|nearest| *nearest < input.len())
+    }
+}
+
+fn expect(input: &[u8], pos: usize, expected: &[u8]) -> Option<usize> {
+    input[pos..]
+        .starts_with(expected)
+        .then_some(pos + expected.len())
+}
+
+/// Pulls the `mul(x,y)`, `do()` and `don't()` instructions out of corrupted memory, skipping
+/// everything else
+pub struct Lexer<'a> {
+    input: &'a [u8],
+    pos: usize,
+    grammar: Grammar,
+    operands: Operands,
+    candidates: Candidates,
+}
+
+impl<'a> Lexer<'a> {
+    pub fn new(input: &'a str) -> Self {
+        Self::with_grammar(input, Grammar::Strict)
+    }
+
+    pub fn with_grammar(input: &'a str, grammar: Grammar) -> Self {
+        Self::from_bytes(input.as_bytes(), grammar)
+    }
+
+    pub fn from_bytes(input: &'a [u8], grammar: Grammar) -> Self {
+        Self {
+            input,
+            pos: 0,
+            grammar,
+            operands: Operands::default(),
+            candidates: Candidates::new([&b"mul"[..], b"do"]),
+        }
+    }
+
+    pub fn with_operands(self, operands: Operands) -> Self {
+        Self { operands, ..self }
+    }
+
+    /// The most bytes the lexer looks at to decide whether an instruction starts at some index
+    pub fn lookahead(&self) -> usize {
+        let number = self.operands.max_digits + (self.operands.signs != Signs::Unsigned) as usize;
+        // mul(x,y) is the longest, unless its numbers are tiny
+        (b"mul(,)".len() + 2 * number).max(b"don't()".len())
+    }
+
+    fn expect(&self, pos: usize, expected: &[u8]) -> Option<usize> {
+        expect(self.input, pos, expected)
+    }
+
+    // tries to read an instruction starting exactly at `pos`
+    fn instruction(&self, pos: usize) -> Option<(Instruction, usize)> {
+        match self.input[pos] {
+            b'm' => {
+                let pos = self.expect(pos, b"mul(")?;
+                let (x, pos) = self.operands.parse(self.input, pos)?;
+                let pos = self.expect(pos, b",")?;
+                let (y, pos) = self.operands.parse(self.input, pos)?;
+                let pos = self.expect(pos, b")")?;
+                Some((Instruction::Mul(x, y), pos))
+            }
+            b'd' if self.grammar == Grammar::Loose => self.loose_do(pos),
+            b'd' => {
+                if let Some(pos) = self.expect(pos, b"do()") {
+                    Some((Instruction::Do, pos))
+                } else {
+                    Some((Instruction::Dont, self.expect(pos, b"don't()")?))
+                }
+            }
+            _ => None,
+        }
+    }
+
+    // how far the text at `pos` got into spelling out an instruction, if it got as far as `mul`
+    // or `do` and wasn't one
+    fn near_miss(&self, pos: usize) -> Option<usize> {
+        let matching = |pos: usize, expected: &[u8]| {
+            let common = self.input[pos..]
+                .iter()
+                .zip(expected)
+                .take_while(|(byte, expected)| byte == expected)
+                .count();
+            pos + common
+        };
+        match self.input[pos] {
+            b'm' => {
+                let end = matching(pos, b"mul(");
+                if end < pos + b"mul".len() {
+                    return None;
+                }
+                let Some((_, end)) = self.operands.parse(self.input, end) else {
+                    return Some(end);
+                };
+                let Some(end) = self.expect(end, b",") else {
+                    return Some(end);
+                };
+                match self.operands.parse(self.input, end) {
+                    Some((_, end)) => Some(matching(end, b")")),
+                    None => Some(end),
+                }
+            }
+            b'd' => {
+                let end = matching(pos, b"do()").max(matching(pos, b"don't()"));
+                (end >= pos + b"do".len()).then_some(end)
+            }
+            _ => None,
+        }
+    }
+
+    /// The next instruction or near miss. Iterating the lexer skips the near misses.
+    pub fn next_lexeme(&mut self) -> Option<Lexeme> {
+        // every instruction and near miss starts with `mul` or `do`, so jump straight to those
+        // with a substring search and only run the full parser there
+        while let Some(start) = self.candidates.next(self.input, self.pos) {
+            if let Some((instruction, end)) = self.instruction(start) {
+                self.pos = end;
+                return Some(Lexeme::Token(Token {
+                    instruction,
+                    start,
+                    end,
+                }));
+            }
+            self.pos = start + 1;
+            if let Some(end) = self.near_miss(start) {
+                return Some(Lexeme::NearMiss { start, end });
+            }
+        }
+        self.pos = self.input.len();
+        None
+    }
+
+    // the token ends as soon as it stops spelling out `don't`, without taking the byte that
+    // broke the pattern. Nothing is read when the input ends mid-keyword.
+    fn loose_do(&self, pos: usize) -> Option<(Instruction, usize)> {
+        let pos = self.expect(pos, b"do")?;
+        match &self.input[pos..] {
+            [] | [b'n'] | [b'n', b'\''] => None,
+            [b'n', b'\'', b't', ..] => Some((Instruction::Dont, pos + 3)),
+            [b'n', b'\'', ..] => Some((Instruction::Do, pos + 2)),
+            [b'n', ..] => Some((Instruction::Do, pos + 1)),
+            _ => Some((Instruction::Do, pos)),
+        }
+    }
+}
+
+impl Iterator for Lexer<'_> {
+    type Item = Token;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Lexeme::Token(token) = self.next_lexeme()? {
+                return Some(token);
+            }
+        }
+    }
+}
+
+/// The name of an instruction and how many numbers it takes
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Signature {
+    pub name: String,
+    pub arity: usize,
+}
+
+/// An instruction read by a `CallLexer`: which signature it matched, its arguments, and the byte
+/// range it was read from
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Call {
+    pub signature: usize,
+    pub args: Vec<i128>,
+    pub start: usize,
+    pub end: usize,
+}
+
+/// Pulls instructions written like `name(1,22,333)` out of corrupted memory, for any set of
+/// names. Where more than one signature could start at the same byte, the earliest one that
+/// reads wins.
+pub struct CallLexer<'a> {
+    input: &'a [u8],
+    pos: usize,
+    signatures: &'a [Signature],
+    operands: Operands,
+    candidates: Candidates,
+}
+
+impl<'a> CallLexer<'a> {
+    pub fn new(input: &'a str, signatures: &'a [Signature]) -> Self {
+        Self {
+            input: input.as_bytes(),
+            pos: 0,
+            signatures,
+            operands: Operands::default(),
+            candidates: Candidates::new(signatures.iter().map(|sig| sig.name.as_bytes())),
+        }
+    }
+
+    pub fn with_operands(self, operands: Operands) -> Self {
+        Self { operands, ..self }
+    }
+
+    // reads the `(a,b,...)` part of an instruction starting at `pos`
+    fn args(&self, mut pos: usize, arity: usize) -> Option<(Vec<i128>, usize)> {
+        pos = expect(self.input, pos, b"(")?;
+        let mut args = Vec::with_capacity(arity);
+        for idx in 0..arity {
+            if idx > 0 {
+                pos = expect(self.input, pos, b",")?;
+            }
+            let (arg, next) = self.operands.parse(self.input, pos)?;
+            args.push(arg);
+            pos = next;
+        }
+        Some((args, expect(self.input, pos, b")")?))
+    }
+
+    // tries to read an instruction starting exactly at `start`
+    fn call(&self, start: usize) -> Option<Call> {
+        self.signatures
+            .iter()
+            .enumerate()
+            .filter(|(_, sig)| self.input[start..].starts_with(sig.name.as_bytes()))
+            .find_map(|(signature, sig)| {
+                let (args, end) = self.args(start + sig.name.len(), sig.arity)?;
+                Some(Call {
+                    signature,
+                    args,
+                    start,
+                    end,
+                })
+            })
+    }
+}
+
+impl Iterator for CallLexer<'_> {
+    type Item = Call;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while let Some(start) = self.candidates.next(self.input, self.pos) {
+            if let Some(call) = self.call(start) {
+                self.pos = call.end;
+                return Some(call);
+            }
+            self.pos = start + 1;
+        }
+        self.pos = self.input.len();
+        None
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use itertools::Itertools;
+
+    fn instructions(input: &str, grammar: Grammar) -> Vec<Instruction> {
+        Lexer::with_grammar(input, grammar)
+            .map(|token| token.instruction)
+            .collect()
+    }
+
+    #[test]
+    fn configurable_operands() {
+        let input = "mul(1234,-5)mul(+6,7)mul(-0,8)";
+        assert_eq!(instructions(input, Grammar::Strict), []);
+
+        let wide = Operands {
+            max_digits: 4,
+            signs: Signs::Minus,
+        };
+        let lexed = Lexer::new(input)
+            .with_operands(wide)
+            .map(|token| token.instruction)
+            .collect_vec();
+        assert_eq!(lexed, [Instruction::Mul(1234, -5), Instruction::Mul(0, 8)]);
+
+        let any = Operands {
+            max_digits: 40,
+            signs: Signs::PlusMinus,
+        };
+        let lexed = Lexer::new(
+            "mul(+6,7)mul(99999999999999999999,-12)mul(1,999999999999999999999999999999999999999)",
+        )
+        .with_operands(any)
+        .map(|token| token.instruction)
+        .collect_vec();
+        assert_eq!(
+            lexed,
+            [
+                Instruction::Mul(6, 7),
+                Instruction::Mul(99999999999999999999, -12)
+            ]
+        );
+    }
+
+    #[test]
+    fn strict_near_misses() {
+        for input in [
+            "do", "dox", "do(", "do)", "do ()", "don", "don'", "don't", "don't(", "dont()",
+            "don'x()", "DO()",
+        ] {
+            assert_eq!(instructions(input, Grammar::Strict), [], "{input}");
+        }
+        assert_eq!(instructions("do()", Grammar::Strict), [Instruction::Do]);
+        assert_eq!(
+            instructions("don't()", Grammar::Strict),
+            [Instruction::Dont]
+        );
+        assert_eq!(
+            instructions("don't()do()", Grammar::Strict),
+            [Instruction::Dont, Instruction::Do]
+        );
+    }
+
+    #[test]
+    fn loose_near_misses() {
+        use Instruction::{Do, Dont};
+        for (input, expected) in [
+            ("dox", vec![Do]),
+            ("do(", vec![Do]),
+            ("done", vec![Do]),
+            ("don'x", vec![Do]),
+            ("don't", vec![Dont]),
+            ("don't(", vec![Dont]),
+            ("undo()?", vec![Do]),
+            // the input runs out before we know what was meant
+            ("do", vec![]),
+            ("don", vec![]),
+            ("don'", vec![]),
+        ] {
+            assert_eq!(instructions(input, Grammar::Loose), expected, "{input}");
+        }
+    }
+
+    #[test]
+    fn lexes_sample() {
+        let tokens =
+            Lexer::new("xmul(2,4)&mul[3,7]!^don't()_mul(5,5)+mul(32,64](mul(11,8)undo()?mul(8,5))")
+                .collect_vec();
+        assert_eq!(
+            tokens
+                .iter()
+                .map(|token| (token.instruction, token.start, token.end))
+                .collect_vec(),
+            [
+                (Instruction::Mul(2, 4), 1, 9),
+                (Instruction::Dont, 20, 27),
+                (Instruction::Mul(5, 5), 28, 36),
+                (Instruction::Mul(11, 8), 48, 57),
+                (Instruction::Do, 59, 63),
+                (Instruction::Mul(8, 5), 64, 72),
+            ]
+        );
+    }
+
+    #[test]
+    fn near_misses() {
+        let mut lexer = Lexer::new("mul[3,7]mul(2,4]mu(1,1)don't_do()mul(5,5)mul(6,x)");
+        let lexemes = std::iter::from_fn(|| lexer.next_lexeme()).collect_vec();
+        assert_eq!(
+            lexemes,
+            [
+                Lexeme::NearMiss { start: 0, end: 3 },
+                Lexeme::NearMiss { start: 8, end: 15 },
+                Lexeme::NearMiss { start: 23, end: 28 },
+                Lexeme::Token(Token {
+                    instruction: Instruction::Do,
+                    start: 29,
+                    end: 33
+                }),
+                Lexeme::Token(Token {
+                    instruction: Instruction::Mul(5, 5),
+                    start: 33,
+                    end: 41
+                }),
+                Lexeme::NearMiss { start: 41, end: 47 },
+            ]
+        );
+    }
+
+    #[test]
+    fn lexes_calls() {
+        let signatures =
+            [("mul", 2), ("do", 0), ("don't", 0), ("neg", 1)].map(|(name, arity)| Signature {
+                name: name.to_string(),
+                arity,
+            });
+        let calls = CallLexer::new("xneg(7)don't()mul(2,4]mumul(3,4)neg(1,2)do()", &signatures)
+            .map(|call| {
+                (
+                    signatures[call.signature].name.as_str(),
+                    call.args,
+                    call.start,
+                )
+            })
+            .collect_vec();
+        assert_eq!(
+            calls,
+            [
+                ("neg", vec![7], 1),
+                ("don't", vec![], 7),
+                ("mul", vec![3, 4], 24),
+                ("do", vec![], 40),
+            ]
+        );
+
+        // the strict grammar is the puzzle's instructions as calls
+        let input = "xmul(2,4)&mul[3,7]!^don't()_mul(5,5)+mul(32,64](mul(11,8)undo()?mul(8,5))";
+        let spans = |calls: CallLexer| calls.map(|call| (call.start, call.end)).collect_vec();
+        assert_eq!(
+            spans(CallLexer::new(input, &signatures)),
+            Lexer::new(input)
+                .map(|token| (token.start, token.end))
+                .collect_vec()
+        );
+    }
+
+    #[test]
+    fn rejects_malformed_muls() {
+        for input in [
+            "mul(1234,5)",
+            "mul(1,)",
+            "mul (1,2)",
+            "mul(1,2",
+            "mul(-1,2)",
+            "mul(,2)",
+        ] {
+            assert_eq!(Lexer::new(input).next(), None, "{input}");
+        }
+        // a failed candidate doesn't hide one that starts inside it
+        assert_eq!(
+            Lexer::new("mumul(3,4)")
+                .map(|t| t.instruction)
+                .collect_vec(),
+            [Instruction::Mul(3, 4)]
+        );
+    }
+}
@@ -0,0 +1,164 @@
+use super::{
+    lexer::{Grammar, Lexer, Operands, Token},
+    Accumulator, Evaluator, OverflowError,
+};
+use std::{
+    error::Error,
+    fmt::Display,
+    io::{self, Read},
+};
+
+#[derive(Debug)]
+pub enum StreamError {
+    Io(io::Error),
+    Overflow(OverflowError),
+}
+
+impl Display for StreamError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            StreamError::Io(err) => write!(f, "failed to read memory: {err}"),
+            StreamError::Overflow(err) => write!(f, "{err}"),
+        }
+    }
+}
+
+impl Error for StreamError {}
+
+/// Evaluates memory read from any `Read` a chunk at a time, so the whole dump never has to be
+/// in memory. Only the tail of a chunk that could still be the start of an instruction is kept
+/// around for the next one.
+pub struct StreamEvaluator {
+    pub grammar: Grammar,
+    pub operands: Operands,
+    pub do_do: bool,
+    pub accumulator: Accumulator,
+    pub chunk_size: usize,
+}
+
+impl StreamEvaluator {
+    pub fn new(do_do: bool) -> Self {
+        Self {
+            grammar: Grammar::Strict,
+            operands: Operands::default(),
+            do_do,
+            accumulator: Accumulator::Checked64,
+            chunk_size: 64 * 1024,
+        }
+    }
+
+    pub fn evaluate(&self, reader: impl Read) -> Result<i128, StreamError> {
+        let mut evaluator = Evaluator::new(self.do_do, self.accumulator);
+        self.for_each_token(reader, |token| {
+            evaluator.feed(token).map_err(StreamError::Overflow)
+        })?;
+        Ok(evaluator.total())
+    }
+
+    /// Calls `f` with every token in the stream, with spans relative to the start of the stream
+    pub fn for_each_token(
+        &self,
+        mut reader: impl Read,
+        mut f: impl FnMut(&Token) -> Result<(), StreamError>,
+    ) -> Result<(), StreamError> {
+        let lookahead = Lexer::from_bytes(&[], self.grammar)
+            .with_operands(self.operands)
+            .lookahead();
+        let mut buffer = Vec::with_capacity(self.chunk_size + lookahead);
+        // where `buffer` starts in the stream
+        let mut offset = 0;
+
+        loop {
+            let carried = buffer.len();
+            buffer.resize(carried + self.chunk_size.max(1), 0);
+            let read = match reader.read(&mut buffer[carried..]) {
+                Ok(read) => read,
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => {
+                    buffer.truncate(carried);
+                    continue;
+                }
+                Err(err) => return Err(StreamError::Io(err)),
+            };
+            buffer.truncate(carried + read);
+            let at_end = read == 0;
+
+            // instructions starting at or after `undecided` might continue into the next chunk
+            let undecided = match at_end {
+                true => buffer.len(),
+                false => (buffer.len() + 1).saturating_sub(lookahead),
+            };
+            let mut resume = undecided;
+            for mut token in Lexer::from_bytes(&buffer, self.grammar).with_operands(self.operands) {
+                if token.start >= undecided {
+                    break;
+                }
+                resume = resume.max(token.end);
+                token.start += offset;
+                token.end += offset;
+                f(&token)?;
+            }
+
+            if at_end {
+                return Ok(());
+            }
+            buffer.drain(..resume.min(buffer.len()));
+            offset += resume;
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::day3::{evaluate, lexer::Signs};
+    use std::fs;
+
+    #[test]
+    fn chunk_boundaries() {
+        let input = fs::read_to_string("input/2024/day3.txt").unwrap();
+        for do_do in [false, true] {
+            let expected = evaluate(Lexer::new(&input), do_do, Accumulator::Checked64).unwrap();
+            for chunk_size in [1, 2, 3, 5, 8, 13, 100, 4096] {
+                let stream = StreamEvaluator {
+                    chunk_size,
+                    ..StreamEvaluator::new(do_do)
+                };
+                assert_eq!(stream.evaluate(input.as_bytes()).unwrap(), expected);
+            }
+        }
+    }
+
+    #[test]
+    fn loose_grammar_and_wide_operands() {
+        let input = "don'tmul(2,2)dox mul(-1000,3)don";
+        for chunk_size in 1..input.len() {
+            let stream = StreamEvaluator {
+                grammar: Grammar::Loose,
+                operands: Operands {
+                    max_digits: 4,
+                    signs: Signs::Minus,
+                },
+                chunk_size,
+                ..StreamEvaluator::new(true)
+            };
+            assert_eq!(stream.evaluate(input.as_bytes()).unwrap(), -3000);
+        }
+    }
+
+    #[test]
+    fn overflow_offsets_are_absolute() {
+        let input = format!("{}mul(3037000500,3037000500)", "x".repeat(50));
+        let stream = StreamEvaluator {
+            operands: Operands {
+                max_digits: 10,
+                signs: Signs::Unsigned,
+            },
+            chunk_size: 7,
+            ..StreamEvaluator::new(false)
+        };
+        match stream.evaluate(input.as_bytes()) {
+            Err(StreamError::Overflow(err)) => assert_eq!((err.start, err.end), (50, 76)),
+            other => panic!("expected an overflow, got {other:?}"),
+        }
+    }
+}
@@ -0,0 +1,147 @@
+use super::{
+    lexer::{Grammar, Instruction, Lexeme, Lexer, Operands, Token},
+    Accumulator, Evaluator, OverflowError,
+};
+use std::fmt::Display;
+
+/// A line of a trace
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Entry<'a> {
+    // `enabled` and `total` are the state after the instruction ran
+    Instruction {
+        token: Token,
+        text: &'a str,
+        enabled: bool,
+        total: i128,
+    },
+    NearMiss {
+        start: usize,
+        text: &'a str,
+    },
+}
+
+impl Display for Entry<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Entry::Instruction {
+                token,
+                text,
+                enabled,
+                total,
+            } => {
+                let state = match enabled {
+                    true => "on",
+                    false => "off",
+                };
+                let operation = match token.instruction {
+                    Instruction::Mul(x, y) if *enabled => format!("{x} * {y}"),
+                    Instruction::Mul(x, y) => format!("{x} * {y} (skipped)"),
+                    Instruction::Do | Instruction::Dont => String::new(),
+                };
+                write!(
+                    f,
+                    "{:>8}  {text:<16}  {state:<3}  {operation:<20}  total {total}",
+                    token.start
+                )
+            }
+            Entry::NearMiss { start, text } => write!(f, "{start:>8}  {text:<16}  rejected"),
+        }
+    }
+}
+
+/// Runs memory like `evaluate`, but keeps a record of every instruction and, if asked for, the
+/// near misses between them
+pub struct Tracer {
+    pub grammar: Grammar,
+    pub operands: Operands,
+    pub do_do: bool,
+    pub accumulator: Accumulator,
+    pub near_misses: bool,
+}
+
+impl Tracer {
+    pub fn new(do_do: bool) -> Self {
+        Self {
+            grammar: Grammar::Strict,
+            operands: Operands::default(),
+            do_do,
+            accumulator: Accumulator::Checked64,
+            near_misses: false,
+        }
+    }
+
+    pub fn trace<'a>(&self, input: &'a str) -> Result<Vec<Entry<'a>>, OverflowError> {
+        let mut lexer = Lexer::with_grammar(input, self.grammar).with_operands(self.operands);
+        let mut evaluator = Evaluator::new(self.do_do, self.accumulator);
+        let mut entries = Vec::new();
+        while let Some(lexeme) = lexer.next_lexeme() {
+            match lexeme {
+                Lexeme::Token(token) => {
+                    evaluator.feed(&token)?;
+                    entries.push(Entry::Instruction {
+                        token,
+                        text: &input[token.start..token.end],
+                        // a mul is traced with whether it counted, do() and don't() with what
+                        // they switched to
+                        enabled: evaluator.mul_enabled(),
+                        total: evaluator.total(),
+                    });
+                }
+                Lexeme::NearMiss { start, end } if self.near_misses => {
+                    entries.push(Entry::NearMiss {
+                        start,
+                        text: &input[start..end],
+                    });
+                }
+                Lexeme::NearMiss { .. } => {}
+            }
+        }
+        Ok(entries)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const SAMPLE_INPUT: &str =
+        "xmul(2,4)&mul[3,7]!^don't()_mul(5,5)+mul(32,64](mul(11,8)undo()?mul(8,5))";
+
+    #[test]
+    fn traces_sample() {
+        let lines = Tracer::new(true)
+            .trace(SAMPLE_INPUT)
+            .unwrap()
+            .iter()
+            .map(|entry| entry.to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            lines,
+            [
+                "       1  mul(2,4)          on   2 * 4                 total 8",
+                "      20  don't()           off                        total 8",
+                "      28  mul(5,5)          off  5 * 5 (skipped)       total 8",
+                "      48  mul(11,8)         off  11 * 8 (skipped)      total 8",
+                "      59  do()              on                         total 8",
+                "      64  mul(8,5)          on   8 * 5                 total 48",
+            ]
+        );
+    }
+
+    #[test]
+    fn near_misses_are_optional() {
+        let mut tracer = Tracer::new(false);
+        assert_eq!(tracer.trace(SAMPLE_INPUT).unwrap().len(), 6);
+        tracer.near_misses = true;
+        let misses = tracer
+            .trace(SAMPLE_INPUT)
+            .unwrap()
+            .into_iter()
+            .filter_map(|entry| match entry {
+                Entry::NearMiss { start, text } => Some((start, text)),
+                Entry::Instruction { .. } => None,
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(misses, [(10, "mul"), (37, "mul(32,64")]);
+    }
+}
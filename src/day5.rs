@@ -1,37 +1,80 @@
 use aoc_runner_derive::{aoc, aoc_generator};
+use index::PageIndex;
 use itertools::Itertools;
-use rule::Rule;
-use std::{cmp::Ordering, str::FromStr};
+use rule::{Rule, RuleParseError};
+use std::{error::Error, fmt::Display, num::ParseIntError, str::FromStr};
 
-type Update = Vec<i32>;
+pub mod analysis;
+pub mod dot;
+pub mod explain;
+pub mod generate;
+pub mod index;
+pub mod orderings;
+pub mod store;
 
-struct DailyInput {
-    rules: Vec<Rule>,
-    updates: Vec<Update>,
+pub type Update = Vec<i32>;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DailyInput {
+    pub rules: Vec<Rule>,
+    pub updates: Vec<Update>,
+}
+
+// Writes the input back out in the puzzle format: the rules, a blank line, then the updates
+impl Display for DailyInput {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.rules
+            .iter()
+            .try_for_each(|rule| writeln!(f, "{rule}"))?;
+        writeln!(f)?;
+        self.updates
+            .iter()
+            .try_for_each(|update| writeln!(f, "{}", update.iter().join(",")))
+    }
 }
 
-mod rule {
-    use std::{fmt::Display, str::FromStr};
+pub mod rule {
+    use std::{fmt::Display, num::ParseIntError, str::FromStr};
 
-    #[derive(Clone, Copy, PartialEq, Eq, Debug)]
-    pub(crate) struct Rule {
-        pub(crate) before: i32,
-        pub(crate) after: i32,
+    #[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+    pub struct Rule {
+        pub before: i32,
+        pub after: i32,
     }
 
-    #[derive(Debug)]
-    pub(crate) enum RuleParseError {
-        ParseIntError,
-        ParsePipeError,
+    #[derive(Clone, Debug, PartialEq, Eq)]
+    pub enum RuleParseError {
+        // column is where the token starts in the rule, starting at 1
+        ParseIntError {
+            column: usize,
+            token: String,
+            source: ParseIntError,
+        },
+        ParsePipeError {
+            token: String,
+        },
     }
     impl FromStr for Rule {
         type Err = RuleParseError;
 
         fn from_str(s: &str) -> Result<Self, Self::Err> {
-            let (lhs, rhs) = s.split_once("|").ok_or(RuleParseError::ParsePipeError)?;
+            let (lhs, rhs) = s
+                .split_once("|")
+                .ok_or_else(|| RuleParseError::ParsePipeError {
+                    token: s.to_string(),
+                })?;
+            let parse_page = |token: &str, column| {
+                token
+                    .parse()
+                    .map_err(|source| RuleParseError::ParseIntError {
+                        column,
+                        token: token.to_string(),
+                        source,
+                    })
+            };
             Ok(Rule {
-                before: lhs.parse().map_err(|_| RuleParseError::ParseIntError)?,
-                after: rhs.parse().map_err(|_| RuleParseError::ParseIntError)?,
+                before: parse_page(lhs, 1)?,
+                after: parse_page(rhs, lhs.len() + 2)?,
             })
         }
     }
@@ -50,78 +93,196 @@ fn try_follows_rule(updates: &Update, r: &Rule) -> Option<bool> {
     Some(before < after)
 }
 
-// Given a list of rules and an update, check to see if the update follows the rules
-fn update_is_valid(rules: &[Rule], update: &Update) -> bool {
-    rules
-        .iter()
-        // if the value is None, then it is valid in this case
-        .all(|rule| try_follows_rule(update, rule).unwrap_or(true))
+#[derive(Debug, PartialEq, Eq)]
+pub enum OrderError {
+    // the pages that could not be placed because they sit on a cycle, or after one
+    Cycle(Vec<i32>),
+    // an update whose rules allow more than one order
+    Ambiguous(Update),
 }
 
-// Given a list of rules and an update, check to see if the update breaks the rules
-fn update_is_invalid(rules: &[Rule], update: &Update) -> bool {
-    rules
-        .iter()
-        .any(|rule| match try_follows_rule(update, rule) {
-            Some(follows_rule) => !follows_rule,
-            None => false,
+impl Display for OrderError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            OrderError::Cycle(pages) => write!(
+                f,
+                "the rules for pages {} contain a cycle",
+                pages.iter().join(",")
+            ),
+            OrderError::Ambiguous(update) => write!(
+                f,
+                "the rules allow more than one order for update {}",
+                update.iter().join(",")
+            ),
+        }
+    }
+}
+
+impl Error for OrderError {}
+
+// Orders the pages of an update using only the rules where both pages are in the update
+pub fn topological_sort(rules: &[Rule], update: &Update) -> Result<Update, OrderError> {
+    PageIndex::new(rules).topological_sort(update)
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ParseErrorKind {
+    MissingPipe,
+    InvalidPage(ParseIntError),
+}
+
+/// A token of the input that couldn't be parsed. Lines and columns start at 1.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ParseError {
+    pub line: usize,
+    pub column: usize,
+    pub token: String,
+    pub kind: ParseErrorKind,
+}
+
+impl ParseError {
+    fn from_rule(line: usize, error: RuleParseError) -> Self {
+        match error {
+            RuleParseError::ParseIntError {
+                column,
+                token,
+                source,
+            } => Self {
+                line,
+                column,
+                token,
+                kind: ParseErrorKind::InvalidPage(source),
+            },
+            RuleParseError::ParsePipeError { token } => Self {
+                line,
+                column: 1,
+                token,
+                kind: ParseErrorKind::MissingPipe,
+            },
+        }
+    }
+}
+
+impl Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "line {}, column {}: ", self.line, self.column)?;
+        match &self.kind {
+            ParseErrorKind::MissingPipe => {
+                write!(f, "expected a rule like a|b, found {:?}", self.token)
+            }
+            ParseErrorKind::InvalidPage(source) => {
+                write!(f, "invalid page number {:?}: {source}", self.token)
+            }
+        }
+    }
+}
+
+impl Error for ParseError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match &self.kind {
+            ParseErrorKind::MissingPipe => None,
+            ParseErrorKind::InvalidPage(source) => Some(source),
+        }
+    }
+}
+
+/// Every bad token found in the input, in the order they appear
+#[derive(Debug, PartialEq, Eq)]
+pub struct ParseErrors(pub Vec<ParseError>);
+
+impl Display for ParseErrors {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0.iter().join("\n"))
+    }
+}
+
+impl Error for ParseErrors {}
+
+// Parses a comma separated update found on line `line` of the input
+fn parse_update_at(line: usize, text: &str) -> Result<Update, Vec<ParseError>> {
+    let mut errors = Vec::new();
+    let mut column = 1;
+    let update = text
+        .split(",")
+        .filter_map(|token| {
+            let page = token
+                .parse()
+                .map_err(|source| {
+                    errors.push(ParseError {
+                        line,
+                        column,
+                        token: token.to_string(),
+                        kind: ParseErrorKind::InvalidPage(source),
+                    })
+                })
+                .ok();
+            column += token.len() + 1;
+            page
         })
+        .collect();
+
+    if errors.is_empty() {
+        Ok(update)
+    } else {
+        Err(errors)
+    }
+}
+
+pub fn parse_update(text: &str) -> Result<Update, ParseErrors> {
+    parse_update_at(1, text).map_err(ParseErrors)
 }
 
 #[aoc_generator(day5)]
-fn parse(input: &str) -> DailyInput {
-    let rules = input
-        .lines()
-        .take_while(|line| !line.is_empty())
-        .map(|line| Rule::from_str(line).unwrap())
+pub fn parse(input: &str) -> Result<DailyInput, ParseErrors> {
+    let mut errors = Vec::new();
+    let mut lines = input.lines().enumerate().map(|(idx, text)| (idx + 1, text));
+
+    let rules = lines
+        .by_ref()
+        .take_while(|(_, text)| !text.is_empty())
+        .filter_map(|(line, text)| {
+            Rule::from_str(text)
+                .map_err(|error| errors.push(ParseError::from_rule(line, error)))
+                .ok()
+        })
         .collect_vec();
 
-    let updates = input
-        .lines()
-        .skip(rules.len() + 1)
-        .map(|line| {
-            line.split(",")
-                .map(|token| token.parse().unwrap())
-                .collect()
+    let updates = lines
+        .filter_map(|(line, text)| {
+            parse_update_at(line, text)
+                .map_err(|update_errors| errors.extend(update_errors))
+                .ok()
         })
         .collect();
-    DailyInput { rules, updates }
+
+    if errors.is_empty() {
+        Ok(DailyInput { rules, updates })
+    } else {
+        Err(ParseErrors(errors))
+    }
 }
 
 #[aoc(day5, part1)]
-fn part1(input: &DailyInput) -> i32 {
+fn part1(input: &DailyInput) -> i64 {
+    let index = PageIndex::new(&input.rules);
     input
         .updates
         .iter()
-        .filter(|&update| update_is_valid(&input.rules, update))
-        .map(|update| update.get(update.len() / 2).copied().unwrap_or_default())
+        .filter(|&update| index.update_is_valid(update))
+        .map(|update| update.get(update.len() / 2).copied().unwrap_or_default() as i64)
         .sum()
 }
 
 #[aoc(day5, part2)]
-fn part2(input: &DailyInput) -> i32 {
-    let mut rule_lookup = [[0; 100]; 100];
-    input
-        .rules
-        .iter()
-        .for_each(|rule| rule_lookup[rule.before as usize][rule.after as usize] = 1);
-
+fn part2(input: &DailyInput) -> Result<i64, OrderError> {
+    let index = PageIndex::new(&input.rules);
     input
         .updates
         .iter()
-        .filter(|update| update_is_invalid(&input.rules, update))
+        .filter(|update| index.update_is_invalid(update))
         .map(|update| {
-            update
-                .iter()
-                .sorted_by(|a, b| {
-                    if rule_lookup[**a as usize][**b as usize] == 1 {
-                        Ordering::Less
-                    } else {
-                        Ordering::Greater
-                    }
-                })
-                .nth(update.len() / 2)
-                .unwrap()
+            let sorted = index.unique_ordering(update)?;
+            Ok(sorted[sorted.len() / 2] as i64)
         })
         .sum()
 }
@@ -131,7 +292,7 @@ mod tests {
     use std::fs;
 
     use super::*;
-    const GIVEN_INPUT: &str = "47|53
+    pub(crate) const GIVEN_INPUT: &str = "47|53
 97|13
 97|61
 97|47
@@ -167,21 +328,66 @@ mod tests {
 
     #[test]
     fn part1_real_input() {
-        assert_eq!(part1(&parse(&get_input())), 4281);
+        assert_eq!(part1(&parse(&get_input()).unwrap()), 4281);
     }
 
     #[test]
     fn part1_sample_input() {
-        assert_eq!(part1(&parse(GIVEN_INPUT)), 143);
+        assert_eq!(part1(&parse(GIVEN_INPUT).unwrap()), 143);
     }
 
     #[test]
     fn part2_real_input() {
-        assert_eq!(part2(&parse(&get_input())), 5466);
+        assert_eq!(part2(&parse(&get_input()).unwrap()), Ok(5466));
     }
 
     #[test]
     fn part2_sample_input() {
-        assert_eq!(part2(&parse(GIVEN_INPUT)), 123);
+        assert_eq!(part2(&parse(GIVEN_INPUT).unwrap()), Ok(123));
+    }
+
+    #[test]
+    fn parse_reports_every_bad_token() {
+        let errors = parse("1|2\n3-4\nx|5\n\n1,2\n1,,y").err().unwrap();
+        assert_eq!(
+            errors
+                .0
+                .iter()
+                .map(|e| (e.line, e.column, e.token.as_str()))
+                .collect_vec(),
+            [(2, 1, "3-4"), (3, 1, "x"), (6, 3, ""), (6, 4, "y")]
+        );
+        assert_eq!(
+            errors.to_string(),
+            "line 2, column 1: expected a rule like a|b, found \"3-4\"
+line 3, column 1: invalid page number \"x\": invalid digit found in string
+line 6, column 3: invalid page number \"\": cannot parse integer from empty string
+line 6, column 4: invalid page number \"y\": invalid digit found in string"
+        );
+    }
+
+    #[test]
+    fn round_trip_real_input() {
+        let input = get_input();
+        assert_eq!(parse(&input).unwrap().to_string(), input);
+    }
+
+    #[test]
+    fn topological_sort_sample() {
+        let input = parse(GIVEN_INPUT).unwrap();
+        assert_eq!(
+            topological_sort(&input.rules, &vec![97, 13, 75, 29, 47]),
+            Ok(vec![97, 75, 47, 29, 13])
+        );
+    }
+
+    #[test]
+    fn topological_sort_cycle() {
+        let rules = ["1|2", "2|3", "3|1", "3|4"].map(|r| Rule::from_str(r).unwrap());
+        assert_eq!(
+            topological_sort(&rules, &vec![4, 1, 2, 3]),
+            Err(OrderError::Cycle(vec![4, 1, 2, 3]))
+        );
+        assert_eq!(topological_sort(&rules, &vec![4, 2, 3]), Ok(vec![2, 3, 4]));
     }
 }
@@ -0,0 +1,261 @@
+use super::{rule::Rule, Update};
+use itertools::Itertools;
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    fmt::Display,
+};
+
+// The rules as an adjacency list over compact page ids
+struct PageGraph {
+    pages: Vec<i32>,
+    successors: Vec<Vec<usize>>,
+}
+
+impl PageGraph {
+    // Keeps only the rules where `keep` accepts both pages
+    fn new(rules: &[Rule], keep: impl Fn(i32) -> bool) -> Self {
+        let mut ids = HashMap::new();
+        let mut pages = Vec::new();
+        let mut successors: Vec<Vec<usize>> = Vec::new();
+        let mut id_of = |page: i32, successors: &mut Vec<Vec<usize>>| {
+            *ids.entry(page).or_insert_with(|| {
+                pages.push(page);
+                successors.push(Vec::new());
+                pages.len() - 1
+            })
+        };
+        for rule in rules.iter().filter(|r| keep(r.before) && keep(r.after)) {
+            let before = id_of(rule.before, &mut successors);
+            let after = id_of(rule.after, &mut successors);
+            successors[before].push(after);
+        }
+        Self { pages, successors }
+    }
+
+    // Tarjan's algorithm, with an explicit call stack so long chains of rules can't overflow
+    fn strongly_connected_components(&self) -> Vec<Vec<usize>> {
+        let len = self.pages.len();
+        let mut index: Vec<Option<usize>> = vec![None; len];
+        let mut low_link = vec![0; len];
+        let mut on_stack = vec![false; len];
+        let mut stack = Vec::new();
+        let mut next_index = 0;
+        let mut components = Vec::new();
+        // each frame is a node and how many of its successors have been visited
+        let mut calls: Vec<(usize, usize)> = Vec::new();
+
+        for root in 0..len {
+            if index[root].is_some() {
+                continue;
+            }
+            calls.push((root, 0));
+            while let Some(&mut (node, ref mut visited)) = calls.last_mut() {
+                if *visited == 0 {
+                    index[node] = Some(next_index);
+                    low_link[node] = next_index;
+                    next_index += 1;
+                    stack.push(node);
+                    on_stack[node] = true;
+                }
+                if let Some(&next) = self.successors[node].get(*visited) {
+                    *visited += 1;
+                    match index[next] {
+                        None => calls.push((next, 0)),
+                        Some(found) if on_stack[next] => {
+                            low_link[node] = low_link[node].min(found);
+                        }
+                        Some(_) => {}
+                    }
+                    continue;
+                }
+
+                calls.pop();
+                if let Some(&(parent, _)) = calls.last() {
+                    low_link[parent] = low_link[parent].min(low_link[node]);
+                }
+                if Some(low_link[node]) == index[node] {
+                    let mut component = Vec::new();
+                    while let Some(member) = stack.pop() {
+                        on_stack[member] = false;
+                        component.push(member);
+                        if member == node {
+                            break;
+                        }
+                    }
+                    components.push(component);
+                }
+            }
+        }
+        components
+    }
+
+    // The shortest cycle through `start` that stays inside `component`
+    fn shortest_cycle(&self, start: usize, component: &[usize]) -> Vec<usize> {
+        let component: HashSet<usize> = component.iter().copied().collect();
+        let mut parent: HashMap<usize, usize> = HashMap::new();
+        let mut queue = VecDeque::from([start]);
+        while let Some(node) = queue.pop_front() {
+            for &next in &self.successors[node] {
+                if next == start {
+                    let mut cycle = vec![node];
+                    while let Some(&prev) = parent.get(cycle.last().unwrap()) {
+                        cycle.push(prev);
+                    }
+                    cycle.reverse();
+                    return cycle;
+                }
+                if component.contains(&next) && !parent.contains_key(&next) {
+                    parent.insert(next, node);
+                    queue.push_back(next);
+                }
+            }
+        }
+        unreachable!("every node of a strongly connected component is on a cycle")
+    }
+}
+
+/// A set of pages that all (transitively) have to come before each other
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Component {
+    pub pages: Vec<i32>,
+    // one concrete cycle through the component, as the rules that form it
+    pub cycle: Vec<Rule>,
+}
+
+impl Display for Component {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "pages {} cycle {}",
+            self.pages.iter().join(","),
+            self.cycle.iter().join(" ")
+        )
+    }
+}
+
+/// The cyclic parts of a rule set
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ConsistencyReport {
+    pub components: Vec<Component>,
+}
+
+impl ConsistencyReport {
+    /// Checks every rule
+    pub fn global(rules: &[Rule]) -> Self {
+        Self::new(&PageGraph::new(rules, |_| true))
+    }
+
+    /// Checks only the rules between pages of `update`
+    pub fn for_update(rules: &[Rule], update: &Update) -> Self {
+        Self::new(&PageGraph::new(rules, |page| update.contains(&page)))
+    }
+
+    fn new(graph: &PageGraph) -> Self {
+        let components = graph
+            .strongly_connected_components()
+            .into_iter()
+            .filter(|component| {
+                component.len() > 1 || graph.successors[component[0]].contains(&component[0])
+            })
+            .map(|component| {
+                let start = *component.iter().min().unwrap();
+                let cycle = graph.shortest_cycle(start, &component);
+                Component {
+                    pages: component
+                        .iter()
+                        .map(|&id| graph.pages[id])
+                        .sorted()
+                        .collect(),
+                    cycle: cycle
+                        .iter()
+                        .circular_tuple_windows()
+                        .map(|(&before, &after)| Rule {
+                            before: graph.pages[before],
+                            after: graph.pages[after],
+                        })
+                        .collect(),
+                }
+            })
+            .sorted_by_key(|component| component.pages[0])
+            .collect();
+        Self { components }
+    }
+
+    pub fn is_consistent(&self) -> bool {
+        self.components.is_empty()
+    }
+}
+
+impl Display for ConsistencyReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.is_consistent() {
+            return write!(f, "consistent");
+        }
+        writeln!(f, "{} cyclic component(s)", self.components.len())?;
+        self.components
+            .iter()
+            .try_for_each(|component| writeln!(f, "  {component}"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::day5::parse;
+    use std::{fs, str::FromStr};
+
+    fn rules(rules: &[&str]) -> Vec<Rule> {
+        rules.iter().map(|r| Rule::from_str(r).unwrap()).collect()
+    }
+
+    #[test]
+    fn finds_cycles() {
+        let rules = rules(&["1|2", "2|3", "3|1", "3|4", "4|5", "5|4", "6|6"]);
+        let report = ConsistencyReport::global(&rules);
+        assert_eq!(report.components.len(), 3);
+        assert_eq!(
+            report.to_string(),
+            "3 cyclic component(s)
+  pages 1,2,3 cycle 1|2 2|3 3|1
+  pages 4,5 cycle 4|5 5|4
+  pages 6 cycle 6|6
+"
+        );
+
+        assert!(ConsistencyReport::for_update(&rules, &vec![1, 2, 4]).is_consistent());
+        let report = ConsistencyReport::for_update(&rules, &vec![3, 4, 5]);
+        assert_eq!(report.components[0].to_string(), "pages 4,5 cycle 4|5 5|4");
+    }
+
+    #[test]
+    fn long_chains() {
+        let len = 200_000;
+        let chain = (0..len)
+            .map(|page| Rule {
+                before: page,
+                after: page + 1,
+            })
+            .collect_vec();
+        assert!(ConsistencyReport::global(&chain).is_consistent());
+
+        let mut cycle = chain;
+        cycle.push(Rule {
+            before: len,
+            after: 0,
+        });
+        let report = ConsistencyReport::global(&cycle);
+        assert_eq!(report.components.len(), 1);
+        assert_eq!(report.components[0].pages.len(), len as usize + 1);
+        assert_eq!(report.components[0].cycle.len(), len as usize + 1);
+    }
+
+    #[test]
+    fn real_input_is_only_cyclic_globally() {
+        let input = parse(&fs::read_to_string("input/2024/day5.txt").unwrap()).unwrap();
+        assert!(!ConsistencyReport::global(&input.rules).is_consistent());
+        assert!(input
+            .updates
+            .iter()
+            .all(|update| ConsistencyReport::for_update(&input.rules, update).is_consistent()));
+    }
+}
@@ -0,0 +1,62 @@
+use super::{try_follows_rule, DailyInput, Update};
+use std::fmt::Write;
+
+impl DailyInput {
+    /// Renders the rules as a Graphviz digraph with an edge from `before` to `after`.
+    /// When an update is given only the rules between its pages are drawn, and the rules it
+    /// breaks are highlighted in red.
+    pub fn to_dot(&self, update: Option<&Update>) -> String {
+        let mut dot = String::from("digraph rules {\n");
+        if let Some(update) = update {
+            update
+                .iter()
+                .for_each(|page| writeln!(dot, "    {page};").unwrap());
+        }
+
+        for rule in &self.rules {
+            let attributes = match update.map(|update| try_follows_rule(update, rule)) {
+                None | Some(Some(true)) => "",
+                Some(Some(false)) => " [color=red, penwidth=2]",
+                // the rule doesn't apply to this update
+                Some(None) => continue,
+            };
+            writeln!(dot, "    {} -> {}{attributes};", rule.before, rule.after).unwrap();
+        }
+        dot.push_str("}\n");
+        dot
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::day5::parse;
+
+    #[test]
+    fn full_rule_set() {
+        let input = parse("1|2\n2|3\n\n1,2,3").unwrap();
+        assert_eq!(
+            input.to_dot(None),
+            "digraph rules {
+    1 -> 2;
+    2 -> 3;
+}
+"
+        );
+    }
+
+    #[test]
+    fn restricted_to_update() {
+        let input = parse("1|2\n2|3\n3|4\n\n3,2,4").unwrap();
+        assert_eq!(
+            input.to_dot(Some(&input.updates[0])),
+            "digraph rules {
+    3;
+    2;
+    4;
+    2 -> 3 [color=red, penwidth=2];
+    3 -> 4;
+}
+"
+        );
+    }
+}
@@ -0,0 +1,323 @@
+use super::{index::PageIndex, rule::Rule, try_follows_rule, OrderError, Update};
+use itertools::Itertools;
+use std::fmt::Display;
+
+/// A rule broken by an update, with the positions of both pages in the update
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Violation {
+    pub rule: Rule,
+    pub before_position: usize,
+    pub after_position: usize,
+}
+
+/// Take `page` out of position `from` and put it back in at position `to`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Move {
+    pub page: i32,
+    pub from: usize,
+    pub to: usize,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Diagnosis {
+    pub update: Update,
+    pub violations: Vec<Violation>,
+    // the fewest moves that turn `update` into an order that follows the rules, applied in order.
+    // `corrected` is the order they lead to.
+    pub moves: Vec<Move>,
+    pub corrected: Update,
+}
+
+impl Diagnosis {
+    pub fn new(rules: &[Rule], update: &Update) -> Result<Self, OrderError> {
+        let violations = rules
+            .iter()
+            .filter(|rule| try_follows_rule(update, rule) == Some(false))
+            .map(|rule| Violation {
+                rule: *rule,
+                before_position: update.iter().position(|p| *p == rule.before).unwrap(),
+                after_position: update.iter().position(|p| *p == rule.after).unwrap(),
+            })
+            .collect();
+        let index = PageIndex::new(rules);
+        // rejects rules with a cycle, which no order can follow
+        index.topological_sort(update)?;
+        let kept = pages_to_keep(&index, update);
+        let corrected = order_around(&index, update, &kept);
+        let moves = moves_into(update, &corrected, &kept);
+        Ok(Self {
+            update: update.clone(),
+            violations,
+            moves,
+            corrected,
+        })
+    }
+
+    pub fn is_valid(&self) -> bool {
+        self.violations.is_empty()
+    }
+}
+
+// before[i][j] is set when the page at position i has to come before the page at position j,
+// by a rule or a chain of rules through other pages of the update
+fn precedence(index: &PageIndex, update: &Update) -> Vec<Vec<bool>> {
+    let mut before = update
+        .iter()
+        .map(|a| {
+            update
+                .iter()
+                .map(|b| index.must_precede(*a, *b))
+                .collect_vec()
+        })
+        .collect_vec();
+    for via in 0..update.len() {
+        for i in 0..update.len() {
+            for j in 0..update.len() {
+                before[i][j] |= before[i][via] && before[via][j];
+            }
+        }
+    }
+    before
+}
+
+// The most pages that can stay where they are, by position: the largest set where no page has to
+// come before one that's ahead of it. The pairs that are out of order form a partial order, so
+// this is its largest antichain, which Dilworth and König's theorems get from a maximum matching
+// between out of order pairs.
+fn pages_to_keep(index: &PageIndex, update: &Update) -> Vec<bool> {
+    let before = precedence(index, update);
+    let len = update.len();
+    // i < j, but the page at j has to come first
+    let inverted = |i: usize, j: usize| i < j && before[j][i];
+
+    // matched[j] is the earlier position matched to the later position j
+    let mut matched: Vec<Option<usize>> = vec![None; len];
+    fn augment(
+        i: usize,
+        inverted: &impl Fn(usize, usize) -> bool,
+        matched: &mut [Option<usize>],
+        seen: &mut [bool],
+    ) -> bool {
+        for j in 0..matched.len() {
+            if inverted(i, j) && !seen[j] {
+                seen[j] = true;
+                if matched[j].is_none_or(|other| augment(other, inverted, matched, seen)) {
+                    matched[j] = Some(i);
+                    return true;
+                }
+            }
+        }
+        false
+    }
+    for i in 0..len {
+        augment(i, &inverted, &mut matched, &mut vec![false; len]);
+    }
+
+    // walk alternating paths from the earlier sides left unmatched. The pages reached as an
+    // earlier side but not as a later side are outside the minimum vertex cover on both sides.
+    let mut earlier = (0..len).map(|i| !matched.contains(&Some(i))).collect_vec();
+    let mut later = vec![false; len];
+    let mut stack = (0..len).filter(|i| earlier[*i]).collect_vec();
+    while let Some(i) = stack.pop() {
+        for j in 0..len {
+            if inverted(i, j) && !later[j] {
+                later[j] = true;
+                if let Some(other) = matched[j].filter(|other| !earlier[*other]) {
+                    earlier[other] = true;
+                    stack.push(other);
+                }
+            }
+        }
+    }
+    (0..len).map(|i| earlier[i] && !later[i]).collect()
+}
+
+// A valid order of the update that keeps the kept pages in the order they're in now. Anything
+// else is left as close to where it was as the rules allow.
+fn order_around(index: &PageIndex, update: &Update, kept: &[bool]) -> Update {
+    let before = precedence(index, update);
+    let len = update.len();
+    let must_precede = |i: usize, j: usize| before[i][j] || (kept[i] && kept[j] && i < j);
+    let mut placed = vec![false; len];
+    let mut order = Vec::with_capacity(len);
+    while order.len() < len {
+        let next = (0..len)
+            .find(|&j| !placed[j] && (0..len).all(|i| placed[i] || !must_precede(i, j)))
+            .expect("the kept pages agree with the rules");
+        placed[next] = true;
+        order.push(update[next]);
+    }
+    order
+}
+
+// The kept pages stay where they are, every other page is moved once, directly behind the page
+// that precedes it in `target`
+fn moves_into(update: &Update, target: &Update, kept: &[bool]) -> Vec<Move> {
+    let mut current = update.clone();
+    let mut moves = Vec::new();
+    for (r, page) in target.iter().enumerate() {
+        if kept[update.iter().position(|p| p == page).unwrap()] {
+            continue;
+        }
+        let from = current.iter().position(|p| p == page).unwrap();
+        current.remove(from);
+        let to = match r {
+            0 => 0,
+            _ => current.iter().position(|p| *p == target[r - 1]).unwrap() + 1,
+        };
+        current.insert(to, *page);
+        moves.push(Move {
+            page: *page,
+            from,
+            to,
+        });
+    }
+    moves
+}
+
+impl Display for Diagnosis {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "update {}", self.update.iter().join(","))?;
+        if self.is_valid() {
+            return writeln!(f, "  valid");
+        }
+        writeln!(f, "  violated rules:")?;
+        for violation in &self.violations {
+            writeln!(
+                f,
+                "    {} ({} at position {}, {} at position {})",
+                violation.rule,
+                violation.rule.before,
+                violation.before_position,
+                violation.rule.after,
+                violation.after_position
+            )?;
+        }
+        writeln!(f, "  moves:")?;
+        for m in &self.moves {
+            writeln!(
+                f,
+                "    move {} from position {} to {}",
+                m.page, m.from, m.to
+            )?;
+        }
+        writeln!(f, "  corrected {}", self.corrected.iter().join(","))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        day5::{parse, tests::GIVEN_INPUT},
+        rng::Rng,
+    };
+    use std::fs;
+
+    fn rule(before: i32, after: i32) -> Rule {
+        Rule { before, after }
+    }
+
+    fn apply(update: &Update, moves: &[Move]) -> Update {
+        let mut update = update.clone();
+        for m in moves {
+            assert_eq!(update.remove(m.from), m.page);
+            update.insert(m.to, m.page);
+        }
+        update
+    }
+
+    #[test]
+    fn explains_invalid_update() {
+        let input = parse(GIVEN_INPUT).unwrap();
+        let diagnosis = Diagnosis::new(&input.rules, &vec![75, 97, 47, 61, 53]).unwrap();
+        assert_eq!(
+            diagnosis.to_string(),
+            "update 75,97,47,61,53
+  violated rules:
+    97|75 (97 at position 1, 75 at position 0)
+  moves:
+    move 75 from position 0 to 1
+  corrected 97,75,47,61,53
+"
+        );
+    }
+
+    #[test]
+    fn moves_to_any_valid_order() {
+        let rules = vec![rule(1, 2), rule(2, 4)];
+        let diagnosis = Diagnosis::new(&rules, &vec![2, 4, 1, 3]).unwrap();
+        assert_eq!(
+            diagnosis.moves,
+            [Move {
+                page: 1,
+                from: 2,
+                to: 0
+            }]
+        );
+        assert_eq!(diagnosis.corrected, [1, 2, 4, 3]);
+
+        // 1 and 3 both fit next to 2, but not together
+        let rules = vec![rule(3, 1)];
+        let diagnosis = Diagnosis::new(&rules, &vec![1, 2, 3]).unwrap();
+        assert_eq!(diagnosis.moves.len(), 1);
+        assert!(PageIndex::new(&rules).update_is_valid(&diagnosis.corrected));
+    }
+
+    // the fewest moves between two orders of the same pages is how many pages aren't on their
+    // longest common subsequence
+    fn fewest_moves(from: &[i32], to: &[i32]) -> usize {
+        let mut common = vec![vec![0; to.len() + 1]; from.len() + 1];
+        for i in 0..from.len() {
+            for j in 0..to.len() {
+                common[i + 1][j + 1] = match from[i] == to[j] {
+                    true => common[i][j] + 1,
+                    false => common[i][j + 1].max(common[i + 1][j]),
+                };
+            }
+        }
+        from.len() - common[from.len()][to.len()]
+    }
+
+    #[test]
+    fn moves_are_minimal_against_brute_force() {
+        let mut rng = Rng(32);
+        for _ in 0..2000 {
+            // rules only ever point from a smaller page to a larger one, so they can't cycle
+            let rules = (0..rng.below(10))
+                .map(|_| {
+                    let (a, b) = (1 + rng.below(7) as i32, 1 + rng.below(7) as i32);
+                    rule(a.min(b), a.max(b))
+                })
+                .filter(|rule| rule.before != rule.after)
+                .collect_vec();
+            let mut update = (1..=7).collect_vec();
+            rng.shuffle(&mut update);
+            update.truncate(2 + rng.below(5));
+
+            let index = PageIndex::new(&rules);
+            let fewest = update
+                .iter()
+                .copied()
+                .permutations(update.len())
+                .filter(|order| index.update_is_valid(order))
+                .map(|order| fewest_moves(&update, &order))
+                .min()
+                .unwrap();
+            let diagnosis = Diagnosis::new(&rules, &update).unwrap();
+            assert_eq!(diagnosis.moves.len(), fewest, "{rules:?} {update:?}");
+            assert_eq!(apply(&update, &diagnosis.moves), diagnosis.corrected);
+            assert!(index.update_is_valid(&diagnosis.corrected));
+        }
+    }
+
+    #[test]
+    fn real_input_moves_fix_every_update() {
+        let input = parse(&fs::read_to_string("input/2024/day5.txt").unwrap()).unwrap();
+        for update in &input.updates {
+            let diagnosis = Diagnosis::new(&input.rules, update).unwrap();
+            assert_eq!(apply(update, &diagnosis.moves), diagnosis.corrected);
+            assert_eq!(diagnosis.is_valid(), diagnosis.moves.is_empty());
+        }
+    }
+}
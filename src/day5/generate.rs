@@ -0,0 +1,162 @@
+use super::{rule::Rule, DailyInput, Update};
+use crate::rng::Rng;
+use std::collections::HashSet;
+
+/// The shape of a generated puzzle
+#[derive(Clone, Debug)]
+pub struct Config {
+    pub pages: usize,
+    pub updates: usize,
+    // updates get a random odd length of at most this many pages
+    pub max_update_len: usize,
+    // page numbers are drawn from 1..=max_page
+    pub max_page: i32,
+    pub seed: u64,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            pages: 49,
+            updates: 200,
+            max_update_len: 23,
+            max_page: 99,
+            seed: 2024,
+        }
+    }
+}
+
+/// A random puzzle along with the answers to both parts
+pub struct Generated {
+    pub input: DailyInput,
+    pub part1: i64,
+    pub part2: i64,
+}
+
+/// Generates a puzzle whose rules all follow one hidden order of the pages, so every update can
+/// be corrected. Each update has a rule for every pair of its pages, which makes its corrected
+/// order unique.
+pub fn generate(config: &Config) -> Generated {
+    assert!(
+        config.pages as i64 <= config.max_page as i64,
+        "not enough page numbers"
+    );
+    assert!(
+        config.pages > 0 || config.updates == 0,
+        "updates need at least one page"
+    );
+    let mut rng = Rng(config.seed);
+
+    // the pages in their hidden order
+    let mut pages = Vec::with_capacity(config.pages);
+    let mut used = HashSet::new();
+    while pages.len() < config.pages {
+        let page = 1 + rng.below(config.max_page as usize) as i32;
+        if used.insert(page) {
+            pages.push(page);
+        }
+    }
+
+    let max_len = config.max_update_len.min(config.pages).max(1);
+    let mut rules = Vec::new();
+    let mut seen_rules = HashSet::new();
+    let mut updates = Vec::with_capacity(config.updates);
+    let (mut part1, mut part2) = (0, 0);
+    // every rank, drawn from with a partial shuffle so an update costs its length, not `pages`
+    let mut deck: Vec<usize> = (0..config.pages).collect();
+    for _ in 0..config.updates {
+        let len = 1 + 2 * rng.below(max_len.div_ceil(2));
+        for i in 0..len {
+            deck.swap(i, i + rng.below(config.pages - i));
+        }
+        let mut ranks = deck[..len].to_vec();
+        ranks.sort_unstable();
+
+        for (i, before) in ranks.iter().enumerate() {
+            for after in &ranks[i + 1..] {
+                if seen_rules.insert((*before, *after)) {
+                    rules.push(Rule {
+                        before: pages[*before],
+                        after: pages[*after],
+                    });
+                }
+            }
+        }
+
+        let middle = pages[ranks[len / 2]] as i64;
+        // half of the updates are left in order
+        if rng.below(2) == 0 {
+            part1 += middle;
+        } else {
+            rng.shuffle(&mut ranks);
+            if ranks.is_sorted() {
+                part1 += middle;
+            } else {
+                part2 += middle;
+            }
+        }
+        updates.push(ranks.iter().map(|rank| pages[*rank]).collect::<Update>());
+    }
+    rng.shuffle(&mut rules);
+
+    Generated {
+        input: DailyInput { rules, updates },
+        part1,
+        part2,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::day5::{parse, part1, part2};
+
+    #[test]
+    fn generated_answers_match() {
+        for seed in 0..10 {
+            let generated = generate(&Config {
+                seed,
+                ..Default::default()
+            });
+            assert_eq!(part1(&generated.input), generated.part1);
+            assert_eq!(part2(&generated.input), Ok(generated.part2));
+        }
+    }
+
+    #[test]
+    fn large_page_numbers_round_trip() {
+        let generated = generate(&Config {
+            pages: 500,
+            updates: 100,
+            max_update_len: 41,
+            max_page: 1_000_000,
+            seed: 7,
+        });
+        let text = generated.input.to_string();
+        let input = parse(&text).unwrap();
+        assert_eq!(input, generated.input);
+        assert_eq!(part2(&input), Ok(generated.part2));
+    }
+
+    #[test]
+    #[should_panic(expected = "updates need at least one page")]
+    fn rejects_updates_without_pages() {
+        generate(&Config {
+            pages: 0,
+            ..Default::default()
+        });
+    }
+
+    #[test]
+    fn many_pages() {
+        let generated = generate(&Config {
+            pages: 200_000,
+            updates: 20_000,
+            max_update_len: 5,
+            max_page: 10_000_000,
+            seed: 11,
+        });
+        assert_eq!(part1(&generated.input), generated.part1);
+        assert_eq!(part2(&generated.input), Ok(generated.part2));
+    }
+}
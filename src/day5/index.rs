@@ -0,0 +1,163 @@
+use super::{rule::Rule, OrderError, Update};
+use std::collections::{HashMap, HashSet, VecDeque};
+
+/// Rule sets with more distinct pages than this keep their rules in a hash set, since the bit
+/// matrix grows with the square of the page count (4096 pages take 2 MiB)
+pub const DENSE_PAGES: usize = 4096;
+
+enum Precedes {
+    // bit `before * ids.len() + after` is set if there is a rule `before|after`
+    Dense(Vec<u64>),
+    Sparse(HashSet<(usize, usize)>),
+}
+
+/// Answers "must page a come before page b" in constant time for any page numbers.
+/// Pages are remapped to compact ids, and the rules are stored as a bit matrix over those ids, or
+/// as a set of id pairs when there are too many pages for the matrix.
+pub struct PageIndex {
+    ids: HashMap<i32, usize>,
+    precedes: Precedes,
+}
+
+impl PageIndex {
+    pub fn new(rules: &[Rule]) -> Self {
+        let mut ids = HashMap::new();
+        for rule in rules {
+            for page in [rule.before, rule.after] {
+                let next_id = ids.len();
+                ids.entry(page).or_insert(next_id);
+            }
+        }
+
+        let len = ids.len();
+        let pairs = rules
+            .iter()
+            .map(|rule| (ids[&rule.before], ids[&rule.after]));
+        let precedes = if len <= DENSE_PAGES {
+            let mut bits = vec![0; (len * len).div_ceil(64)];
+            for (before, after) in pairs {
+                let bit = before * len + after;
+                bits[bit / 64] |= 1 << (bit % 64);
+            }
+            Precedes::Dense(bits)
+        } else {
+            Precedes::Sparse(pairs.collect())
+        };
+        Self { ids, precedes }
+    }
+
+    /// True if a rule says `before` has to be printed before `after`
+    #[inline]
+    pub fn must_precede(&self, before: i32, after: i32) -> bool {
+        let (Some(&before), Some(&after)) = (self.ids.get(&before), self.ids.get(&after)) else {
+            return false;
+        };
+        match &self.precedes {
+            Precedes::Dense(bits) => {
+                let bit = before * self.ids.len() + after;
+                bits[bit / 64] & (1 << (bit % 64)) != 0
+            }
+            Precedes::Sparse(pairs) => pairs.contains(&(before, after)),
+        }
+    }
+
+    // Given an update, check to see if the update follows the rules
+    pub fn update_is_valid(&self, update: &[i32]) -> bool {
+        update.iter().enumerate().all(|(idx, after)| {
+            update[idx + 1..]
+                .iter()
+                .all(|before| !self.must_precede(*before, *after))
+        })
+    }
+
+    // Given an update, check to see if the update breaks the rules
+    pub fn update_is_invalid(&self, update: &[i32]) -> bool {
+        !self.update_is_valid(update)
+    }
+
+    // Orders the pages of an update using only the rules where both pages are in the update.
+    // Pages that aren't constrained relative to each other keep their original order.
+    pub fn topological_sort(&self, update: &[i32]) -> Result<Update, OrderError> {
+        let mut in_degree: Vec<usize> = update
+            .iter()
+            .map(|after| {
+                update
+                    .iter()
+                    .filter(|before| self.must_precede(**before, *after))
+                    .count()
+            })
+            .collect();
+
+        let mut ready: VecDeque<usize> = (0..update.len()).filter(|i| in_degree[*i] == 0).collect();
+        let mut sorted = Vec::with_capacity(update.len());
+        while let Some(idx) = ready.pop_front() {
+            sorted.push(update[idx]);
+            for next in 0..update.len() {
+                if self.must_precede(update[idx], update[next]) {
+                    in_degree[next] -= 1;
+                    if in_degree[next] == 0 {
+                        ready.push_back(next);
+                    }
+                }
+            }
+        }
+
+        if sorted.len() == update.len() {
+            Ok(sorted)
+        } else {
+            let cycle = update
+                .iter()
+                .zip(in_degree)
+                .filter(|(_, degree)| *degree > 0)
+                .map(|(page, _)| *page)
+                .collect();
+            Err(OrderError::Cycle(cycle))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    #[test]
+    fn large_page_numbers() {
+        let rules = ["150|7", "7|100000", "-3|150"].map(|r| Rule::from_str(r).unwrap());
+        let index = PageIndex::new(&rules);
+        assert!(index.must_precede(150, 7));
+        assert!(!index.must_precede(7, 150));
+        assert!(!index.must_precede(-3, 7));
+        assert!(!index.must_precede(42, 7));
+
+        assert!(index.update_is_valid(&[-3, 150, 42, 100000]));
+        assert!(index.update_is_invalid(&[7, 150]));
+        assert_eq!(
+            index.topological_sort(&[100000, 42, 7, 150, -3]),
+            Ok(vec![42, -3, 150, 7, 100000])
+        );
+    }
+
+    #[test]
+    fn many_pages() {
+        // a chain through more pages than the bit matrix is used for
+        let pages = (0..100_000).map(|page| page * 3 - 7).collect::<Vec<_>>();
+        let rules = pages
+            .windows(2)
+            .map(|pair| Rule {
+                before: pair[0],
+                after: pair[1],
+            })
+            .collect::<Vec<_>>();
+        let index = PageIndex::new(&rules);
+        assert!(matches!(index.precedes, Precedes::Sparse(_)));
+        assert!(index.must_precede(pages[500], pages[501]));
+        assert!(!index.must_precede(pages[501], pages[500]));
+        assert!(!index.must_precede(pages[500], pages[502]));
+        assert!(index.update_is_valid(&pages[1000..1010]));
+        assert_eq!(
+            index.topological_sort(&[pages[9], pages[7], pages[8]]),
+            Ok(vec![pages[7], pages[8], pages[9]])
+        );
+    }
+}
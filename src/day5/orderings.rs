@@ -0,0 +1,216 @@
+use super::{index::PageIndex, OrderError, Update};
+use itertools::Itertools;
+use std::{
+    cmp::Reverse,
+    collections::{BinaryHeap, HashMap},
+    fmt::Display,
+};
+
+/// The most sets of placed pages `count_orderings` memoises before giving up
+pub const COUNT_STATES: usize = 1 << 18;
+
+/// Every valid way of ordering the pages of one update
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Orderings {
+    // None if the count doesn't fit in a u128, the update has more than 128 pages, or counting
+    // would take more than COUNT_STATES states
+    pub count: Option<u128>,
+    pub unique: bool,
+    // the valid ordering that compares smallest page by page
+    pub smallest: Update,
+}
+
+impl Display for Orderings {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.count {
+            Some(count) => write!(f, "{count} ordering(s)")?,
+            None => write!(f, "too many orderings to count")?,
+        }
+        write!(f, ", smallest {}", self.smallest.iter().join(","))
+    }
+}
+
+impl PageIndex {
+    pub fn orderings(&self, update: &[i32]) -> Result<Orderings, OrderError> {
+        let smallest = self.smallest_ordering(update)?;
+        let unique = self.unique_ordering(update).is_ok();
+        Ok(Orderings {
+            count: self.count_orderings(update),
+            unique,
+            smallest,
+        })
+    }
+
+    /// The sorted update, or an error if the rules allow more than one order
+    pub fn unique_ordering(&self, update: &[i32]) -> Result<Update, OrderError> {
+        let sorted = self.topological_sort(update)?;
+        // the order is unique exactly when every neighbouring pair is pinned down by a rule
+        if sorted
+            .iter()
+            .tuple_windows()
+            .all(|(before, after)| self.must_precede(*before, *after))
+        {
+            Ok(sorted)
+        } else {
+            Err(OrderError::Ambiguous(update.to_vec()))
+        }
+    }
+
+    // Kahn's algorithm, always placing the smallest page that is ready
+    pub fn smallest_ordering(&self, update: &[i32]) -> Result<Update, OrderError> {
+        let mut in_degree = update
+            .iter()
+            .map(|after| {
+                update
+                    .iter()
+                    .filter(|before| self.must_precede(**before, *after))
+                    .count()
+            })
+            .collect_vec();
+        let mut ready: BinaryHeap<_> = (0..update.len())
+            .filter(|i| in_degree[*i] == 0)
+            .map(|i| Reverse((update[i], i)))
+            .collect();
+
+        let mut sorted = Vec::with_capacity(update.len());
+        while let Some(Reverse((page, idx))) = ready.pop() {
+            sorted.push(page);
+            for next in 0..update.len() {
+                if self.must_precede(update[idx], update[next]) {
+                    in_degree[next] -= 1;
+                    if in_degree[next] == 0 {
+                        ready.push(Reverse((update[next], next)));
+                    }
+                }
+            }
+        }
+
+        if sorted.len() == update.len() {
+            Ok(sorted)
+        } else {
+            self.topological_sort(update)
+        }
+    }
+
+    /// Counts the valid orderings by memoising on the set of pages already placed. This is
+    /// cheap when the rules nearly fix the order, but the number of sets is exponential in the
+    /// number of pages that are free relative to each other, so it gives up with None once it
+    /// has seen `COUNT_STATES` of them.
+    pub fn count_orderings(&self, update: &[i32]) -> Option<u128> {
+        fn count(
+            placed: u128,
+            full: u128,
+            preds: &[u128],
+            memo: &mut HashMap<u128, Option<u128>>,
+        ) -> Option<u128> {
+            if placed == full {
+                return Some(1);
+            }
+            if let Some(known) = memo.get(&placed) {
+                return *known;
+            }
+            if memo.len() >= COUNT_STATES {
+                return None;
+            }
+            let mut total: Option<u128> = Some(0);
+            for (idx, pred) in preds.iter().enumerate() {
+                let bit = 1 << idx;
+                if placed & bit == 0 && pred & !placed == 0 {
+                    total = count(placed | bit, full, preds, memo)
+                        .and_then(|rest| total?.checked_add(rest));
+                    if total.is_none() {
+                        break;
+                    }
+                }
+            }
+            memo.insert(placed, total);
+            total
+        }
+
+        if update.len() > 128 {
+            return None;
+        }
+        // bitset of the pages that have to come before each page
+        let preds = update
+            .iter()
+            .map(|after| {
+                update
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, before)| self.must_precede(**before, *after))
+                    .fold(0u128, |mask, (idx, _)| mask | 1 << idx)
+            })
+            .collect_vec();
+        let full = match update.len() {
+            128 => u128::MAX,
+            len => (1 << len) - 1,
+        };
+        count(0, full, &preds, &mut HashMap::new())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::day5::{parse, rule::Rule, tests::GIVEN_INPUT};
+    use std::str::FromStr;
+
+    fn index(rules: &[&str]) -> PageIndex {
+        PageIndex::new(
+            &rules
+                .iter()
+                .map(|r| Rule::from_str(r).unwrap())
+                .collect_vec(),
+        )
+    }
+
+    #[test]
+    fn sample_orderings_are_unique() {
+        let input = parse(GIVEN_INPUT).unwrap();
+        let index = PageIndex::new(&input.rules);
+        for update in &input.updates {
+            let orderings = index.orderings(update).unwrap();
+            assert!(orderings.unique);
+            assert_eq!(orderings.count, Some(1));
+        }
+    }
+
+    #[test]
+    fn partial_orders() {
+        // 1 and 2 both come before 3, but are free relative to each other. 9 is free entirely
+        let index = index(&["2|3", "1|3"]);
+        let orderings = index.orderings(&[3, 2, 1, 9]).unwrap();
+        assert_eq!(orderings.count, Some(8));
+        assert!(!orderings.unique);
+        assert_eq!(orderings.smallest, vec![1, 2, 3, 9]);
+        assert_eq!(orderings.to_string(), "8 ordering(s), smallest 1,2,3,9");
+        assert_eq!(
+            index.unique_ordering(&[3, 2, 1]),
+            Err(OrderError::Ambiguous(vec![3, 2, 1]))
+        );
+    }
+
+    #[test]
+    fn count_overflow_and_cycles() {
+        let index = index(&["1|2", "2|1"]);
+        assert_eq!(index.count_orderings(&[1, 2]), Some(0));
+        assert!(index.orderings(&[1, 2]).is_err());
+        // interleaving three chains of 42 pages has far more orderings than a u128 can hold
+        let rules = (0..126)
+            .filter(|page| page % 42 != 41)
+            .map(|page| Rule {
+                before: page,
+                after: page + 1,
+            })
+            .collect_vec();
+        let chains = PageIndex::new(&rules);
+        assert_eq!(chains.count_orderings(&(0..126).collect_vec()), None);
+    }
+
+    #[test]
+    fn gives_up_on_free_pages() {
+        let free = index(&[]);
+        assert_eq!(free.count_orderings(&(1..=10).collect_vec()), Some(3628800));
+        assert_eq!(free.count_orderings(&(1..=40).collect_vec()), None);
+    }
+}
@@ -0,0 +1,283 @@
+use super::{rule::Rule, Update};
+use itertools::Itertools;
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    error::Error,
+    fmt::Display,
+};
+
+/// What a store does with a rule that would close a cycle
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CyclePolicy {
+    Reject,
+    // keep the rule, but remember that it closed a cycle
+    Flag,
+}
+
+/// A rule that was turned away because it would close a cycle
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RuleRejected {
+    pub rule: Rule,
+    // starts with the rejected rule
+    pub cycle: Vec<Rule>,
+}
+
+impl Display for RuleRejected {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "rule {} would close the cycle {}",
+            self.rule,
+            self.cycle.iter().join(" ")
+        )
+    }
+}
+
+impl Error for RuleRejected {}
+
+/// What changed when a rule was added
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Added {
+    // set when the rule was flagged for closing this cycle
+    pub cycle: Option<Vec<Rule>>,
+    // the stored updates that were valid before this rule and aren't anymore
+    pub invalidated: Vec<usize>,
+}
+
+struct StoredUpdate {
+    positions: HashMap<i32, usize>,
+    // number of stored rules the update breaks
+    violations: usize,
+}
+
+/// Rules that arrive one at a time. Every rule is checked for cycles the moment it is added,
+/// and the updates kept in the store are re-validated against only the new rule.
+pub struct RuleStore {
+    policy: CyclePolicy,
+    // only cycles among these pages are looked for, None means every page
+    scope: Option<HashSet<i32>>,
+    rules: Vec<Rule>,
+    seen: HashSet<Rule>,
+    successors: HashMap<i32, Vec<i32>>,
+    flagged: Vec<Rule>,
+    updates: Vec<StoredUpdate>,
+    // the stored updates each page appears in
+    updates_with: HashMap<i32, Vec<usize>>,
+}
+
+impl RuleStore {
+    pub fn new(policy: CyclePolicy) -> Self {
+        Self {
+            policy,
+            scope: None,
+            rules: Vec::new(),
+            seen: HashSet::new(),
+            successors: HashMap::new(),
+            flagged: Vec::new(),
+            updates: Vec::new(),
+            updates_with: HashMap::new(),
+        }
+    }
+
+    /// A store that only looks for cycles among `pages`, since most full rule sets are cyclic
+    pub fn scoped(policy: CyclePolicy, pages: impl IntoIterator<Item = i32>) -> Self {
+        Self {
+            scope: Some(pages.into_iter().collect()),
+            ..Self::new(policy)
+        }
+    }
+
+    pub fn rules(&self) -> &[Rule] {
+        &self.rules
+    }
+
+    /// The rules that were kept even though they closed a cycle
+    pub fn flagged(&self) -> &[Rule] {
+        &self.flagged
+    }
+
+    fn in_scope(&self, page: i32) -> bool {
+        self.scope
+            .as_ref()
+            .is_none_or(|scope| scope.contains(&page))
+    }
+
+    // The rules on the shortest in-scope path from `from` to `to`, if there is one
+    fn path(&self, from: i32, to: i32) -> Option<Vec<Rule>> {
+        let mut parent = HashMap::from([(from, from)]);
+        let mut queue = VecDeque::from([from]);
+        while let Some(page) = queue.pop_front() {
+            if page == to {
+                let mut path = Vec::new();
+                let mut after = to;
+                while after != from {
+                    let before = parent[&after];
+                    path.push(Rule { before, after });
+                    after = before;
+                }
+                path.reverse();
+                return Some(path);
+            }
+            for next in self.successors.get(&page).into_iter().flatten() {
+                if self.in_scope(*next) && !parent.contains_key(next) {
+                    parent.insert(*next, page);
+                    queue.push_back(*next);
+                }
+            }
+        }
+        None
+    }
+
+    pub fn add_rule(&mut self, rule: Rule) -> Result<Added, RuleRejected> {
+        if !self.seen.insert(rule) {
+            return Ok(Added::default());
+        }
+
+        let cycle = if self.in_scope(rule.before) && self.in_scope(rule.after) {
+            self.path(rule.after, rule.before)
+                .map(|path| std::iter::once(rule).chain(path).collect_vec())
+        } else {
+            None
+        };
+        if let Some(cycle) = &cycle {
+            match self.policy {
+                CyclePolicy::Reject => {
+                    self.seen.remove(&rule);
+                    return Err(RuleRejected {
+                        rule,
+                        cycle: cycle.clone(),
+                    });
+                }
+                CyclePolicy::Flag => self.flagged.push(rule),
+            }
+        }
+
+        self.rules.push(rule);
+        self.successors
+            .entry(rule.before)
+            .or_default()
+            .push(rule.after);
+
+        let mut invalidated = Vec::new();
+        for idx in self.updates_with.get(&rule.before).into_iter().flatten() {
+            let update = &mut self.updates[*idx];
+            if let Some(after) = update.positions.get(&rule.after) {
+                if *after < update.positions[&rule.before] {
+                    if update.violations == 0 {
+                        invalidated.push(*idx);
+                    }
+                    update.violations += 1;
+                }
+            }
+        }
+        Ok(Added { cycle, invalidated })
+    }
+
+    /// Stores an update so it is re-validated as rules are added, returning its id
+    pub fn add_update(&mut self, update: &Update) -> usize {
+        let idx = self.updates.len();
+        let positions: HashMap<i32, usize> = update
+            .iter()
+            .enumerate()
+            .map(|(position, page)| (*page, position))
+            .collect();
+        let violations = positions
+            .iter()
+            .map(|(page, position)| {
+                self.successors
+                    .get(page)
+                    .into_iter()
+                    .flatten()
+                    .filter(|after| positions.get(after).is_some_and(|after| after < position))
+                    .count()
+            })
+            .sum();
+        for page in positions.keys() {
+            self.updates_with.entry(*page).or_default().push(idx);
+        }
+        self.updates.push(StoredUpdate {
+            positions,
+            violations,
+        });
+        idx
+    }
+
+    pub fn update_is_valid(&self, update: usize) -> bool {
+        self.updates[update].violations == 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::day5::{index::PageIndex, parse, tests::GIVEN_INPUT};
+    use std::{fs, str::FromStr};
+
+    fn rule(rule: &str) -> Rule {
+        Rule::from_str(rule).unwrap()
+    }
+
+    #[test]
+    fn rejects_and_flags_cycles() {
+        let mut store = RuleStore::new(CyclePolicy::Reject);
+        assert!(store.add_rule(rule("1|2")).is_ok());
+        assert!(store.add_rule(rule("2|3")).is_ok());
+        let rejected = store.add_rule(rule("3|1")).unwrap_err();
+        assert_eq!(
+            rejected.to_string(),
+            "rule 3|1 would close the cycle 3|1 1|2 2|3"
+        );
+        assert_eq!(store.rules().len(), 2);
+
+        // 2 isn't in scope, so 1|2 2|3 3|1 isn't a cycle among the pages we care about
+        let mut store = RuleStore::scoped(CyclePolicy::Reject, [1, 3]);
+        ["1|2", "2|3", "3|1"]
+            .into_iter()
+            .for_each(|r| assert_eq!(store.add_rule(rule(r)), Ok(Added::default())));
+
+        let mut store = RuleStore::new(CyclePolicy::Flag);
+        store.add_rule(rule("1|2")).unwrap();
+        let added = store.add_rule(rule("2|1")).unwrap();
+        assert_eq!(added.cycle, Some(vec![rule("2|1"), rule("1|2")]));
+        assert_eq!(store.flagged(), [rule("2|1")]);
+    }
+
+    #[test]
+    fn revalidates_updates() {
+        let input = parse(GIVEN_INPUT).unwrap();
+        let index = PageIndex::new(&input.rules);
+        let mut store = RuleStore::new(CyclePolicy::Reject);
+        let ids = input
+            .updates
+            .iter()
+            .map(|update| store.add_update(update))
+            .collect_vec();
+        assert!(ids.iter().all(|id| store.update_is_valid(*id)));
+
+        let mut invalidated = Vec::new();
+        for rule in &input.rules {
+            invalidated.extend(store.add_rule(*rule).unwrap().invalidated);
+        }
+        assert_eq!(invalidated.into_iter().sorted().collect_vec(), [3, 4, 5]);
+        for (id, update) in ids.iter().zip(&input.updates) {
+            assert_eq!(store.update_is_valid(*id), index.update_is_valid(update));
+        }
+    }
+
+    #[test]
+    fn real_input_is_acyclic_per_update() {
+        let input = parse(&fs::read_to_string("input/2024/day5.txt").unwrap()).unwrap();
+        let mut global = RuleStore::new(CyclePolicy::Reject);
+        let rejected = input
+            .rules
+            .iter()
+            .filter(|rule| global.add_rule(**rule).is_err())
+            .count();
+        assert!(rejected > 0);
+
+        for update in &input.updates {
+            let mut store = RuleStore::scoped(CyclePolicy::Reject, update.iter().copied());
+            assert!(input.rules.iter().all(|rule| store.add_rule(*rule).is_ok()));
+        }
+    }
+}
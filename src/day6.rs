@@ -1,15 +1,56 @@
+use crate::grid;
 use aoc_runner_derive::aoc;
 use itertools::Itertools;
+use std::{error::Error, fmt::Display};
 
-enum Direction {
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Direction {
     Up,
     Right,
     Down,
     Left,
 }
 
+impl Direction {
+    /// The direction a guard glyph (`^`, `>`, `v` or `<`) is facing
+    pub fn from_glyph(glyph: u8) -> Option<Self> {
+        match glyph {
+            b'^' => Some(Direction::Up),
+            b'>' => Some(Direction::Right),
+            b'v' => Some(Direction::Down),
+            b'<' => Some(Direction::Left),
+            _ => None,
+        }
+    }
+
+    #[inline(always)]
+    fn rotate_right(self) -> Self {
+        match self {
+            Direction::Up => Direction::Right,
+            Direction::Right => Direction::Down,
+            Direction::Down => Direction::Left,
+            Direction::Left => Direction::Up,
+        }
+    }
+
+    #[inline(always)]
+    fn rotate_left(self) -> Self {
+        match self {
+            Direction::Up => Direction::Left,
+            Direction::Right => Direction::Up,
+            Direction::Down => Direction::Right,
+            Direction::Left => Direction::Down,
+        }
+    }
+
+    #[inline(always)]
+    fn reverse(self) -> Self {
+        self.rotate_right().rotate_right()
+    }
+}
+
 #[inline(always)]
-fn get_vec(direction: &Direction, line_len: isize) -> (isize, isize) {
+fn get_vec(direction: Direction, line_len: isize) -> (isize, isize) {
     match direction {
         Direction::Up => (0, -line_len),
         Direction::Right => (1, 0),
@@ -18,113 +59,291 @@ fn get_vec(direction: &Direction, line_len: isize) -> (isize, isize) {
     }
 }
 
-#[inline(always)]
-fn rotate(direction: &Direction) -> Direction {
-    match direction {
-        Direction::Up => Direction::Right,
-        Direction::Right => Direction::Down,
-        Direction::Down => Direction::Left,
-        Direction::Left => Direction::Up,
+/// What the guard does when the cell in front of it is blocked
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Turn {
+    Right,
+    Left,
+    Reverse,
+}
+
+impl Turn {
+    #[inline(always)]
+    fn apply(self, direction: Direction) -> Direction {
+        match self {
+            Turn::Right => direction.rotate_right(),
+            Turn::Left => direction.rotate_left(),
+            Turn::Reverse => direction.reverse(),
+        }
     }
 }
 
-type TraverserItem = (u8, usize);
-struct Traverser<'a> {
-    next_item: Option<TraverserItem>,
+/// The rules a guard follows while walking the map
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Rules {
+    pub on_obstacle: Turn,
+    // every glyph that the guard can't walk through
+    pub obstacles: Vec<u8>,
+}
+
+impl Default for Rules {
+    fn default() -> Self {
+        Self {
+            on_obstacle: Turn::Right,
+            obstacles: vec![b'#'],
+        }
+    }
+}
+
+impl Rules {
+    #[inline(always)]
+    fn is_obstacle(&self, byte: u8) -> bool {
+        self.obstacles.contains(&byte)
+    }
+}
+
+/// A single step of the guard's walk
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct GuardState {
+    // index of the guard's cell in the input
+    pub position: usize,
+    pub direction: Direction,
+    // true if the guard had to turn before stepping onto this cell
+    pub turned: bool,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum TraverserError {
+    NoGuard,
+}
+
+impl Display for TraverserError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TraverserError::NoGuard => write!(f, "no guard (^, >, v or <) found on the map"),
+        }
+    }
+}
+
+impl Error for TraverserError {}
+
+/// Every guard on the map, in the order they appear in the input
+pub fn find_guards(input: &str) -> Vec<GuardState> {
+    input
+        .bytes()
+        .enumerate()
+        .filter_map(|(position, byte)| {
+            Direction::from_glyph(byte).map(|direction| GuardState {
+                position,
+                direction,
+                turned: false,
+            })
+        })
+        .collect()
+}
+
+pub struct Traverser<'a> {
+    next_item: Option<GuardState>,
     line_len: usize,
-    direction: Direction,
-    move_amt: isize,
-    current_idx: usize,
-    input: &'a str,
+    rules: Rules,
+    input: &'a [u8],
 }
 
-trait Traversable<'a> {
-    fn traverse(&self) -> Traverser<'a>;
+pub trait Traversable<'a> {
+    fn traverse(&self) -> Result<Traverser<'a>, TraverserError>;
+    fn traverse_with(&self, rules: Rules) -> Result<Traverser<'a>, TraverserError>;
 }
 
 impl<'a> Traverser<'a> {
-    fn new(input: &'a str) -> Self {
-        let line_len = input.lines().next().unwrap().len() + 1;
-        let current_location = input.as_bytes().iter().position(|c| *c == b'^').unwrap();
-        let move_vec = get_vec(&Direction::Up, line_len.try_into().unwrap());
+    /// Walks the first guard found on the map
+    pub fn new(input: &'a str, rules: Rules) -> Result<Self, TraverserError> {
+        let start = find_guards(input)
+            .into_iter()
+            .next()
+            .ok_or(TraverserError::NoGuard)?;
+        Ok(Self::from_state(input, rules, start))
+    }
+
+    /// Walks a guard starting from `start`, ignoring any guard glyphs on the map
+    pub fn from_state(input: &'a str, rules: Rules, start: GuardState) -> Self {
+        let line_len = input.find('\n').unwrap_or(input.len()) + 1;
         Self {
-            direction: Direction::Up,
-            move_amt: move_vec.0 + move_vec.1,
-            current_idx: current_location,
             line_len,
-            input,
-            next_item: input
-                .as_bytes()
-                .get(current_location)
-                .map(|byte| (*byte, current_location)),
+            rules,
+            input: input.as_bytes(),
+            next_item: Some(start),
         }
     }
 
-    fn progress(&mut self) -> Option<(u8, usize)> {
-        let mut new_idx = self.current_idx.checked_add_signed(self.move_amt)?;
-        let mut next_item = self.input.as_bytes().get(new_idx)?;
-        //println!("new_idx: {new_idx}, next_item: {:?}", *next_item as char);
-        match next_item {
-            // TODO i could change the interface of this iterator to return \n and just use the
-            // .take_while fn
+    /// One independent traverser per guard on the map
+    pub fn all(input: &'a str, rules: Rules) -> Result<Vec<Self>, TraverserError> {
+        let guards = find_guards(input);
+        if guards.is_empty() {
+            return Err(TraverserError::NoGuard);
+        }
+        Ok(guards
+            .into_iter()
+            .map(|start| Self::from_state(input, rules.clone(), start))
+            .collect())
+    }
+
+    // returns the index one step from `position` in `direction`, if it is still on the map
+    #[inline(always)]
+    fn step(&self, position: usize, direction: Direction) -> Option<usize> {
+        let move_vec = get_vec(direction, self.line_len.try_into().unwrap());
+        let new_idx = position.checked_add_signed(move_vec.0 + move_vec.1)?;
+        match self.input.get(new_idx)? {
             b'\n' => None,
-            b'#' => {
-                while *next_item == b'#' {
-                    // need to rotate, and update the state
-                    self.direction = rotate(&self.direction);
-                    let move_vec = get_vec(&self.direction, self.line_len.try_into().unwrap());
-                    self.move_amt = move_vec.0 + move_vec.1;
-
-                    new_idx = self.current_idx.checked_add_signed(self.move_amt)?;
-                    next_item = self.input.as_bytes().get(new_idx)?;
-                    self.current_idx = new_idx;
-                    //println!(
-                    //    "INNER: new_idx: {}, next_item: {:?}",
-                    //    new_idx, *next_item as char
-                    //);
-                }
-                Some((*next_item, new_idx))
-            }
-            _ => {
-                // this includes the . and the ^ characters
-                self.current_idx = new_idx;
-                Some((*next_item, new_idx))
+            _ => Some(new_idx),
+        }
+    }
+
+    fn progress(&self, current: GuardState) -> Option<GuardState> {
+        let mut direction = current.direction;
+        // a guard can turn at most 3 times before it has tried every direction
+        for turns in 0..4 {
+            let new_idx = self.step(current.position, direction)?;
+            if !self.rules.is_obstacle(self.input[new_idx]) {
+                return Some(GuardState {
+                    position: new_idx,
+                    direction,
+                    turned: turns > 0,
+                });
             }
+            direction = self.rules.on_obstacle.apply(direction);
         }
+        // boxed in on all sides
+        None
     }
 }
 
 impl<'a> Traversable<'a> for &'a str {
-    fn traverse(&self) -> Traverser<'a> {
-        Traverser::new(self)
+    fn traverse(&self) -> Result<Traverser<'a>, TraverserError> {
+        Traverser::new(self, Rules::default())
+    }
+
+    fn traverse_with(&self, rules: Rules) -> Result<Traverser<'a>, TraverserError> {
+        Traverser::new(self, rules)
     }
 }
 
 impl Iterator for Traverser<'_> {
-    // The byte of the char, and the index of the char
-    type Item = (u8, usize);
+    type Item = GuardState;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let item = self.next_item?;
+        self.next_item = self.progress(item);
+        Some(item)
+    }
+}
+
+/// Moves every guard on the map one step per tick. Each item holds the state of every guard,
+/// or None once that guard has left the map.
+pub struct Lockstep<'a> {
+    guards: Vec<Traverser<'a>>,
+}
+
+impl<'a> Lockstep<'a> {
+    pub fn new(input: &'a str, rules: Rules) -> Result<Self, TraverserError> {
+        Ok(Self {
+            guards: Traverser::all(input, rules)?,
+        })
+    }
+}
+
+impl Iterator for Lockstep<'_> {
+    type Item = Vec<Option<GuardState>>;
 
     fn next(&mut self) -> Option<Self::Item> {
-        let item = self.next_item;
-        self.next_item = self.progress();
-        item
+        let tick = self
+            .guards
+            .iter_mut()
+            .map(|guard| guard.next())
+            .collect_vec();
+        tick.iter().any(Option::is_some).then_some(tick)
+    }
+}
+
+/// Statistics about a single guard's walk
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PathReport {
+    // number of moves from one cell to the next
+    pub steps: usize,
+    // number of cells the guard turned on before stepping
+    pub turns: usize,
+    // most steps taken without turning
+    pub longest_run: usize,
+    pub distinct_cells: usize,
+    // cells the guard crossed while facing more than one direction
+    pub multi_direction_cells: usize,
+    // true if the guard ended up repeating a state instead of leaving the map
+    pub loops: bool,
+    // number of times each index of the input was visited
+    pub visits: Vec<usize>,
+}
+
+impl PathReport {
+    /// Walks the guard until it leaves the map or starts looping
+    pub fn new(input: &str, traverser: Traverser) -> Self {
+        let mut visits = vec![0; input.len()];
+        // bitset of the directions each cell was visited in
+        let mut headings = vec![0u8; input.len()];
+        let mut report = Self {
+            steps: 0,
+            turns: 0,
+            longest_run: 0,
+            distinct_cells: 0,
+            multi_direction_cells: 0,
+            loops: false,
+            visits: Vec::new(),
+        };
+        let mut run = 0;
+
+        for (idx, state) in traverser.enumerate() {
+            let heading = 1 << state.direction as u8;
+            if headings[state.position] & heading != 0 {
+                report.loops = true;
+                break;
+            }
+            headings[state.position] |= heading;
+            visits[state.position] += 1;
+
+            if idx > 0 {
+                report.steps += 1;
+                run = if state.turned { 1 } else { run + 1 };
+            }
+            if state.turned {
+                report.turns += 1;
+            }
+            report.longest_run = report.longest_run.max(run);
+        }
+
+        report.distinct_cells = visits.iter().filter(|count| **count > 0).count();
+        report.multi_direction_cells = headings.iter().filter(|h| h.count_ones() > 1).count();
+        report.visits = visits;
+        report
+    }
+
+    /// Renders the map with each visited cell replaced by its visit count
+    pub fn heat_map(&self, input: &str) -> String {
+        grid::render(input, |idx, byte| grid::heat_glyph(self.visits[idx], byte))
     }
 }
 
 #[aoc(day6, part1)]
-fn part1(input: &str) -> i32 {
-    input
-        .traverse()
-        .map(|(_ch, idx)| idx)
+fn part1(input: &str) -> Result<i32, TraverserError> {
+    Ok(input
+        .traverse()?
+        .map(|state| state.position)
         .sorted_unstable()
         .dedup()
         .count()
         .try_into()
-        .unwrap()
+        .unwrap())
 }
 
 #[aoc(day6, part2)]
-fn part2(input: &str) -> i32 {
+fn part2(_input: &str) -> i32 {
     0
 }
 
@@ -152,12 +371,106 @@ mod tests {
 
     #[test]
     fn part1_given_input() {
-        assert_eq!(part1(SAMPLE_INPUT), 41);
+        assert_eq!(part1(SAMPLE_INPUT), Ok(41));
     }
 
     #[test]
     fn part1_real_input() {
-        assert_eq!(part1(&get_input()), 5331);
+        assert_eq!(part1(&get_input()), Ok(5331));
+    }
+
+    #[test]
+    fn traverse_reports_turns() {
+        let first_turn = SAMPLE_INPUT.traverse().unwrap().find(|state| state.turned);
+        assert_eq!(
+            first_turn,
+            Some(GuardState {
+                position: 16,
+                direction: Direction::Right,
+                turned: true,
+            })
+        );
+    }
+
+    #[test]
+    fn traverse_with_custom_rules() {
+        let reverse = Rules {
+            on_obstacle: Turn::Reverse,
+            ..Default::default()
+        };
+        let path = ".#.\n...\n.^."
+            .traverse_with(reverse)
+            .unwrap()
+            .collect_vec();
+        assert_eq!(
+            path.iter().map(|s| (s.position, s.direction)).collect_vec(),
+            [(9, Direction::Up), (5, Direction::Up), (9, Direction::Down)]
+        );
+
+        let left = Rules {
+            on_obstacle: Turn::Left,
+            obstacles: vec![b'#', b'O'],
+        };
+        let path = ".O.\n...\n.^.".traverse_with(left).unwrap().collect_vec();
+        assert_eq!(
+            path.iter().map(|s| (s.position, s.turned)).collect_vec(),
+            [(9, false), (5, false), (4, true)]
+        );
+    }
+
+    #[test]
+    fn start_from_any_guard_glyph() {
+        let path = "...\n..<\n...".traverse().unwrap().collect_vec();
+        assert_eq!(
+            path.iter().map(|s| (s.position, s.direction)).collect_vec(),
+            [
+                (6, Direction::Left),
+                (5, Direction::Left),
+                (4, Direction::Left)
+            ]
+        );
+        assert_eq!("...\n...".traverse().err(), Some(TraverserError::NoGuard));
+    }
+
+    #[test]
+    fn multiple_guards() {
+        let input = "v..\n...\n..^";
+        let independent = Traverser::all(input, Rules::default())
+            .unwrap()
+            .into_iter()
+            .map(|guard| guard.map(|s| s.position).collect_vec())
+            .collect_vec();
+        assert_eq!(independent, [vec![0, 4, 8], vec![10, 6, 2]]);
+
+        let ticks = Lockstep::new(input, Rules::default())
+            .unwrap()
+            .collect_vec();
+        assert_eq!(ticks.len(), 3);
+        assert_eq!(
+            ticks[1].iter().map(|s| s.map(|s| s.position)).collect_vec(),
+            [Some(4), Some(6)]
+        );
+    }
+
+    #[test]
+    fn path_report() {
+        let report = PathReport::new(SAMPLE_INPUT, SAMPLE_INPUT.traverse().unwrap());
+        assert_eq!(report.distinct_cells, 41);
+        assert_eq!(report.steps, 44);
+        assert_eq!(report.turns, 10);
+        assert_eq!(report.longest_run, 6);
+        assert_eq!(report.multi_direction_cells, 4);
+        assert!(!report.loops);
+
+        let heat_map = report.heat_map(SAMPLE_INPUT);
+        assert_eq!(heat_map.lines().nth(6), Some(".#1121211."));
+    }
+
+    #[test]
+    fn path_report_detects_loops() {
+        let input = ".#..\n...#\n#^..\n..#.";
+        let report = PathReport::new(input, input.traverse().unwrap());
+        assert!(report.loops);
     }
 
     #[test]
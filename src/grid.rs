@@ -0,0 +1,40 @@
+/// Redraws a newline separated grid, replacing every cell with the byte returned by `cell`.
+/// `cell` is given the index of the cell in `input` and its original byte.
+pub fn render(input: &str, mut cell: impl FnMut(usize, u8) -> u8) -> String {
+    input
+        .bytes()
+        .enumerate()
+        .map(|(idx, byte)| match byte {
+            b'\n' => '\n',
+            _ => cell(idx, byte) as char,
+        })
+        .collect()
+}
+
+/// The glyph used for a cell visited `count` times: the original byte when unvisited, the count
+/// itself up to 9, and `+` for anything higher
+pub fn heat_glyph(count: usize, original: u8) -> u8 {
+    match count {
+        0 => original,
+        1..=9 => b'0' + count as u8,
+        _ => b'+',
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn render_keeps_line_breaks() {
+        let rendered = render("ab\ncd\n", |idx, byte| if idx == 4 { b'X' } else { byte });
+        assert_eq!(rendered, "ab\ncX\n");
+    }
+
+    #[test]
+    fn heat_glyphs() {
+        assert_eq!(heat_glyph(0, b'#'), b'#');
+        assert_eq!(heat_glyph(3, b'.'), b'3');
+        assert_eq!(heat_glyph(12, b'.'), b'+');
+    }
+}
@@ -1,5 +1,3 @@
-mod day5;
-mod day6;
 extern crate aoc_runner;
 
 #[macro_use]
@@ -10,5 +8,9 @@ pub mod day1;
 pub mod day2;
 pub mod day3;
 pub mod day4;
+pub mod day5;
+pub mod day6;
+pub mod grid;
+mod rng;
 
 aoc_lib! { year = 2024 }